@@ -0,0 +1,239 @@
+//! Benchmarking setup for the asset pools pallet
+
+use super::*;
+
+#[allow(unused)]
+use crate::Pallet as AssetPools;
+use frame_benchmarking::{account, benchmarks, whitelisted_caller};
+use frame_support::sp_runtime::traits::{One, Saturating};
+use frame_support::traits::tokens::fungibles::{Create, Inspect, Mutate};
+use frame_support::traits::{Currency, Get};
+use frame_system::RawOrigin;
+
+const SEED: u32 = 0;
+
+/// The asset staked in the benchmarks and its derivative.
+const ASSET: u32 = 1_000;
+const STAKED_ASSET: u32 = 1_001;
+
+fn assert_last_event<T: Config>(generic_event: <T as Config>::Event) {
+    frame_system::Pallet::<T>::assert_last_event(generic_event.into());
+}
+
+// A stake well above the minimum balance of the asset.
+fn stake_amount<T: Config>() -> BalanceOf<T> {
+    BalanceOf::<T>::from(1_000_000u32)
+}
+
+// Enough of `Currency` to reserve the deposit of a few pools.
+fn deposit_funds<T: Config>() -> DepositBalanceOf<T> {
+    T::Currency::minimum_balance()
+        .max(T::PoolDeposit::get())
+        .saturating_mul(10u32.into())
+}
+
+// An account holding the deposit of a pool, so it can also hold a non-sufficient derivative,
+// and enough of the staked asset to stake `stake_amount` many times.
+fn funded_account<T: Config>(name: &'static str, index: u32) -> T::AccountId
+where
+    T::AssetId: From<u32>,
+{
+    let who = account(name, index, SEED);
+    fund::<T>(&who);
+    who
+}
+
+fn fund<T: Config>(who: &T::AccountId)
+where
+    T::AssetId: From<u32>,
+{
+    T::Currency::make_free_balance_be(who, deposit_funds::<T>());
+    T::Assets::mint_into(
+        ASSET.into(),
+        who,
+        stake_amount::<T>().saturating_mul(100u32.into()),
+    )
+    .expect("the asset exists");
+}
+
+// Create the staked asset and a pool over it owned by `owner`, with the highest reward rate and
+// commission and a reward period of a single block, so every block distributes rewards.
+fn create_pool<T: Config>(owner: &T::AccountId) -> PoolId
+where
+    T::AssetId: From<u32>,
+{
+    let admin: T::AccountId = account("admin", 0, SEED);
+    let _ = T::Assets::create(ASSET.into(), admin, true, One::one());
+    fund::<T>(owner);
+    let pool_id = NextPoolId::<T>::get();
+    AssetPools::<T>::create_pool(
+        RawOrigin::Signed(owner.clone()).into(),
+        ASSET.into(),
+        STAKED_ASSET.into(),
+        T::MaxRewardRate::get(),
+        T::MaxCommission::get(),
+        T::MinBlockToUnlock::get(),
+        One::one(),
+    )
+    .expect("the owner is funded");
+    AssetPools::<T>::fund_pot(
+        RawOrigin::Signed(owner.clone()).into(),
+        pool_id,
+        stake_amount::<T>().saturating_mul(10u32.into()),
+    )
+    .expect("the owner holds the asset");
+    pool_id
+}
+
+fn stake_from<T: Config>(who: &T::AccountId, pool_id: PoolId) {
+    AssetPools::<T>::stake(
+        RawOrigin::Signed(who.clone()).into(),
+        pool_id,
+        stake_amount::<T>(),
+    )
+    .expect("the staker is funded");
+}
+
+// Queue `count` unbonding requests of `who`, unstaking a small part of its position each time.
+fn fill_unbonding<T: Config>(who: &T::AccountId, pool_id: PoolId, count: u32)
+where
+    T::AssetId: From<u32>,
+{
+    let amount = T::Assets::balance(STAKED_ASSET.into(), who)
+        / T::MaxUnbondingChunks::get().saturating_mul(10).into();
+    for _ in 0..count {
+        AssetPools::<T>::unstake(RawOrigin::Signed(who.clone()).into(), pool_id, amount)
+            .expect("the staker holds the derivative");
+    }
+}
+
+// Move past the cooldown of the pool, making a reward distribution due.
+fn advance<T: Config>() {
+    let n = frame_system::Pallet::<T>::block_number()
+        + T::MinBlockToUnlock::get().saturating_add(1).into();
+    frame_system::Pallet::<T>::set_block_number(n);
+}
+
+benchmarks! {
+    where_clause { where T::AssetId: From<u32> }
+
+    create_pool {
+        let admin: T::AccountId = account("admin", 0, SEED);
+        T::Assets::create(ASSET.into(), admin, true, One::one())?;
+        let caller: T::AccountId = whitelisted_caller();
+        T::Currency::make_free_balance_be(&caller, deposit_funds::<T>());
+    }: _(
+        RawOrigin::Signed(caller.clone()),
+        ASSET.into(),
+        STAKED_ASSET.into(),
+        T::MaxRewardRate::get(),
+        T::MaxCommission::get(),
+        T::MinBlockToUnlock::get(),
+        One::one()
+    )
+    verify {
+        assert_last_event::<T>(
+            Event::<T>::PoolCreated(0, caller, ASSET.into(), STAKED_ASSET.into()).into(),
+        );
+    }
+
+    // The rewards due are distributed with the old parameters first.
+    set_pool_parameters {
+        let caller: T::AccountId = whitelisted_caller();
+        let pool_id = create_pool::<T>(&caller);
+        let staker = funded_account::<T>("staker", 0);
+        stake_from::<T>(&staker, pool_id);
+        advance::<T>();
+        let pooled = TotalPooled::<T>::get(pool_id);
+    }: _(
+        RawOrigin::Signed(caller),
+        pool_id,
+        T::MaxRewardRate::get(),
+        T::MaxCommission::get(),
+        T::MaxBlockToUnlock::get(),
+        T::MaxRewardPeriod::get()
+    )
+    verify {
+        assert!(TotalPooled::<T>::get(pool_id) > pooled);
+    }
+
+    // The funded pot is swept to the owner and the derivative destroyed.
+    close_pool {
+        let caller: T::AccountId = whitelisted_caller();
+        let pool_id = create_pool::<T>(&caller);
+    }: _(RawOrigin::Signed(caller.clone()), pool_id)
+    verify {
+        assert_last_event::<T>(Event::<T>::PoolClosed(pool_id, caller).into());
+    }
+
+    // A reward distribution is due before the new derivative is priced.
+    stake {
+        let owner: T::AccountId = account("owner", 0, SEED);
+        let pool_id = create_pool::<T>(&owner);
+        let caller: T::AccountId = whitelisted_caller();
+        fund::<T>(&caller);
+        stake_from::<T>(&caller, pool_id);
+        advance::<T>();
+        let pooled = TotalPooled::<T>::get(pool_id);
+        let amount = stake_amount::<T>();
+    }: _(RawOrigin::Signed(caller), pool_id, amount)
+    verify {
+        assert!(TotalPooled::<T>::get(pool_id) > pooled.saturating_add(amount));
+    }
+
+    // A reward distribution is due and the unbonding queue is filled.
+    unstake {
+        let owner: T::AccountId = account("owner", 0, SEED);
+        let pool_id = create_pool::<T>(&owner);
+        let caller: T::AccountId = whitelisted_caller();
+        fund::<T>(&caller);
+        stake_from::<T>(&caller, pool_id);
+        let queued = T::MaxUnbondingChunks::get().saturating_sub(1);
+        fill_unbonding::<T>(&caller, pool_id, queued);
+        advance::<T>();
+        let amount = T::Assets::balance(STAKED_ASSET.into(), &caller) / 10u32.into();
+    }: _(RawOrigin::Signed(caller.clone()), pool_id, amount)
+    verify {
+        assert_eq!(Unbonding::<T>::get(pool_id, &caller).len() as u32, queued.saturating_add(1));
+    }
+
+    // Every request of a full queue is released.
+    withdraw_unbonded {
+        let owner: T::AccountId = account("owner", 0, SEED);
+        let pool_id = create_pool::<T>(&owner);
+        let caller: T::AccountId = whitelisted_caller();
+        fund::<T>(&caller);
+        stake_from::<T>(&caller, pool_id);
+        fill_unbonding::<T>(&caller, pool_id, T::MaxUnbondingChunks::get());
+        advance::<T>();
+    }: _(RawOrigin::Signed(caller.clone()), pool_id)
+    verify {
+        assert!(Unbonding::<T>::get(pool_id, &caller).is_empty());
+    }
+
+    // The receiver doesn't hold the derivative yet.
+    transfer {
+        let owner: T::AccountId = account("owner", 0, SEED);
+        let pool_id = create_pool::<T>(&owner);
+        let caller: T::AccountId = whitelisted_caller();
+        fund::<T>(&caller);
+        stake_from::<T>(&caller, pool_id);
+        let recv = funded_account::<T>("recv", 0);
+        let amount = T::Assets::balance(STAKED_ASSET.into(), &caller) / 2u32.into();
+    }: _(RawOrigin::Signed(caller), pool_id, recv.clone(), amount)
+    verify {
+        assert_eq!(T::Assets::balance(STAKED_ASSET.into(), &recv), amount);
+    }
+
+    fund_pot {
+        let owner: T::AccountId = account("owner", 0, SEED);
+        let pool_id = create_pool::<T>(&owner);
+        let caller = funded_account::<T>("funder", 0);
+        let amount = stake_amount::<T>();
+    }: _(RawOrigin::Signed(caller.clone()), pool_id, amount)
+    verify {
+        assert_last_event::<T>(Event::<T>::PotFunded(pool_id, caller, amount).into());
+    }
+
+    impl_benchmark_test_suite!(AssetPools, crate::mock::new_test_ext(), crate::mock::Test);
+}
@@ -0,0 +1,764 @@
+//! Permissionless liquid staking pools over `fungibles` assets.
+//!
+//! The simple pool pallet binds a single pair of `Currency` types, so each runtime gets a single
+//! pool. Here any account can create a pool by reserving `Config::PoolDeposit`. Every pool gets a
+//! `PoolId` and its own sub-accounts, stakes an asset of `Config::Assets` (e.g. `pallet-assets`)
+//! and mints its own derivative asset, with its own reward rate, cooldown, reward period and owner
+//! commission, bounded by the constants of the pallet.
+//!
+//! The rewards raise the exchange rate: every `reward_period` blocks `percentage` of the pooled
+//! asset moves from the pot of the pool into the pool, minus the commission paid to the owner, so
+//! the derivative is redeemed for more. The distributions due are settled when the pool is used,
+//! so no hook iterates over the pools.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+pub use pallet::*;
+
+use codec::{Decode, Encode, MaxEncodedLen};
+use frame_support::sp_runtime::Perbill;
+use frame_support::traits::Currency;
+use frame_support::RuntimeDebug;
+use scale_info::TypeInfo;
+use simple_pool_primitives::mul_div;
+pub use simple_pool_primitives::UnbondingChunk;
+
+type BalanceOf<T> = <T as Config>::Balance;
+type DepositBalanceOf<T> =
+    <<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
+type PoolInfoOf<T> = PoolInfo<
+    <T as frame_system::Config>::AccountId,
+    <T as Config>::AssetId,
+    DepositBalanceOf<T>,
+    <T as frame_system::Config>::BlockNumber,
+>;
+
+/// The identifier of a pool, assigned by `create_pool` in increasing order.
+pub type PoolId = u32;
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests;
+
+#[cfg(feature = "runtime-benchmarks")]
+mod benchmarking;
+
+pub mod weights;
+
+/// The owner, the assets and the parameters of a pool.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct PoolInfo<AccountId, AssetId, DepositBalance, BlockNumber> {
+    /// The account that created the pool. It receives the commission and can close the pool.
+    pub owner: AccountId,
+    /// The deposit reserved from the owner, returned by `close_pool`.
+    pub deposit: DepositBalance,
+    /// The asset staked in the pool.
+    pub asset: AssetId,
+    /// The derivative asset minted by `stake`, owned by the pool account.
+    pub staked_asset: AssetId,
+    /// The share of the pooled asset moved from the pot every `reward_period` blocks.
+    pub percentage: Perbill,
+    /// The share of every reward distribution paid to the owner instead of the pool.
+    pub commission: Perbill,
+    /// The number of blocks an unbonding request waits before it can be withdrawn.
+    pub block_to_unlock: u32,
+    /// The number of blocks between two reward distributions.
+    pub reward_period: BlockNumber,
+}
+
+#[frame_support::pallet]
+pub mod pallet {
+    use super::{
+        mul_div, BalanceOf, DepositBalanceOf, PoolId, PoolInfo, PoolInfoOf, UnbondingChunk,
+    };
+    use frame_support::pallet_prelude::*;
+    use frame_support::sp_runtime::traits::{
+        AccountIdConversion, AtLeast32BitUnsigned, One, SaturatedConversion, Saturating, Zero,
+    };
+    use frame_support::sp_runtime::{
+        ArithmeticError, DispatchResult, FixedPointNumber, FixedPointOperand, FixedU128, Perbill,
+    };
+    use frame_support::traits::tokens::fungibles::{
+        self, Create, Destroy, Inspect, Mutate, Transfer,
+    };
+    use frame_support::traits::ReservableCurrency;
+    use frame_support::PalletId;
+    use frame_system::pallet_prelude::*;
+
+    use crate::weights::WeightInfo;
+
+    #[pallet::config]
+    pub trait Config: frame_system::Config {
+        /// Because this pallet emits events, it depends on the runtime's definition of an event.
+        type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
+
+        /// The identifier of the assets, both staked and derivative.
+        type AssetId: Member + Parameter + Copy + MaxEncodedLen;
+
+        /// The balance type of the assets.
+        type Balance: Parameter
+            + Member
+            + AtLeast32BitUnsigned
+            + FixedPointOperand
+            + MaxEncodedLen
+            + Default
+            + Copy
+            + MaybeSerializeDeserialize;
+
+        /// The assets staked in the pools and the derivatives they mint, e.g. `pallet-assets`.
+        type Assets: fungibles::Inspect<Self::AccountId, AssetId = Self::AssetId, Balance = Self::Balance>
+            + fungibles::Mutate<Self::AccountId>
+            + fungibles::Transfer<Self::AccountId>
+            + fungibles::Create<Self::AccountId>
+            + fungibles::Destroy<Self::AccountId>;
+
+        /// The currency reserving the deposit of the pools.
+        type Currency: ReservableCurrency<Self::AccountId>;
+
+        /// The deposit reserved from the owner of a pool until it is closed.
+        #[pallet::constant]
+        type PoolDeposit: Get<DepositBalanceOf<Self>>;
+
+        /// The pool and pot accounts of every pool are derived from it.
+        #[pallet::constant]
+        type PalletId: Get<PalletId>;
+
+        /// The maximum number of pending unbonding requests per account and pool.
+        #[pallet::constant]
+        type MaxUnbondingChunks: Get<u32>;
+
+        /// The minimum `block_to_unlock` of a pool.
+        #[pallet::constant]
+        type MinBlockToUnlock: Get<u32>;
+
+        /// The maximum `block_to_unlock` of a pool.
+        #[pallet::constant]
+        type MaxBlockToUnlock: Get<u32>;
+
+        /// The maximum `percentage` of a pool.
+        #[pallet::constant]
+        type MaxRewardRate: Get<Perbill>;
+
+        /// The maximum `reward_period` of a pool.
+        #[pallet::constant]
+        type MaxRewardPeriod: Get<Self::BlockNumber>;
+
+        /// The maximum `commission` of a pool.
+        #[pallet::constant]
+        type MaxCommission: Get<Perbill>;
+
+        /// Weight information for extrinsics in this pallet.
+        type WeightInfo: WeightInfo;
+    }
+
+    #[pallet::pallet]
+    #[pallet::generate_store(pub(super) trait Store)]
+    pub struct Pallet<T>(_);
+
+    #[pallet::storage]
+    #[pallet::getter(fn next_pool_id)]
+    /// The identifier given to the next pool.
+    pub type NextPoolId<T: Config> = StorageValue<_, PoolId, ValueQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn pools)]
+    /// The open pools.
+    pub type Pools<T: Config> = StorageMap<_, Twox64Concat, PoolId, PoolInfoOf<T>>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn total_pooled)]
+    /// The staked asset backing the derivative of each pool.
+    /// Together with the supply of the derivative it defines the exchange rate of the pool.
+    pub type TotalPooled<T: Config> = StorageMap<_, Twox64Concat, PoolId, BalanceOf<T>, ValueQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn last_reward_block)]
+    /// The block of the last reward distribution of each pool.
+    pub type LastRewardBlock<T: Config> =
+        StorageMap<_, Twox64Concat, PoolId, T::BlockNumber, ValueQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn unbonding)]
+    /// The pending withdrawals of each account in each pool, oldest first.
+    pub type Unbonding<T: Config> = StorageDoubleMap<
+        _,
+        Twox64Concat,
+        PoolId,
+        Blake2_128Concat,
+        T::AccountId,
+        BoundedVec<UnbondingChunk<BalanceOf<T>, T::BlockNumber>, T::MaxUnbondingChunks>,
+        ValueQuery,
+    >;
+
+    #[pallet::event]
+    #[pallet::generate_deposit(pub(super) fn deposit_event)]
+    pub enum Event<T: Config> {
+        /// Event emitted when a pool has been created. [pool_id, owner, asset, staked_asset]
+        PoolCreated(PoolId, T::AccountId, T::AssetId, T::AssetId),
+
+        /// Event emitted when the owner changed the parameters of a pool. [pool_id, percentage, commission, block_to_unlock, reward_period]
+        PoolParametersSet(PoolId, Perbill, Perbill, u32, T::BlockNumber),
+
+        /// Event emitted when the owner closed an empty pool. [pool_id, owner]
+        PoolClosed(PoolId, T::AccountId),
+
+        /// Event emitted when an account of a closed pool still holds other assets, so it keeps
+        /// the provider added by `create_pool`. [pool_id, account]
+        ProviderKept(PoolId, T::AccountId),
+
+        /// Event emitted when an account stakes in a pool. [pool_id, who, amount, minted]
+        Staked(PoolId, T::AccountId, BalanceOf<T>, BalanceOf<T>),
+
+        /// Event emitted when an account unstakes from a pool. [pool_id, who, burned, redeemed, unlock_at]
+        Unstaked(
+            PoolId,
+            T::AccountId,
+            BalanceOf<T>,
+            BalanceOf<T>,
+            T::BlockNumber,
+        ),
+
+        /// Event emitted when the unlocked requests of an account have been withdrawn. [pool_id, who, amount]
+        Withdrawn(PoolId, T::AccountId, BalanceOf<T>),
+
+        /// Event emitted when the derivative of a pool has been transferred. [pool_id, from, to, amount]
+        StakedAssetTransferred(PoolId, T::AccountId, T::AccountId, BalanceOf<T>),
+
+        /// Event emitted when the pot of a pool has been funded. [pool_id, who, amount]
+        PotFunded(PoolId, T::AccountId, BalanceOf<T>),
+
+        /// Event emitted when the pot of a pool paid the rewards due, scaled down if it is short. [pool_id, pooled, commission, required]
+        RewardsDistributed(PoolId, BalanceOf<T>, BalanceOf<T>, BalanceOf<T>),
+    }
+
+    // Errors inform users that something went wrong.
+    #[pallet::error]
+    pub enum Error<T> {
+        /// There is no pool with the given id.
+        UnknownPool,
+
+        /// Only the owner of the pool can call this function.
+        NotPoolOwner,
+
+        /// The pool still has derivative in circulation or pending unbonding requests.
+        PoolNotEmpty,
+
+        /// The caller can't reserve `PoolDeposit`.
+        InsufficientDeposit,
+
+        /// The asset to stake doesn't exist.
+        UnknownAsset,
+
+        /// The derivative asset could not be created, e.g. because its identifier is in use.
+        StakedAssetInUse,
+
+        /// A pool can't mint the asset it stakes.
+        SameAsset,
+
+        /// An account is trying to stake more than it can transfer.
+        NotEnoughAsset,
+
+        /// An account is trying to unstake or transfer more derivative than it has.
+        NotEnoughStakedAsset,
+
+        /// An account is trying to transfer funds to itself.
+        TransferToSelf,
+
+        /// An account is trying to stake/unstake a 0 amount of tokens,
+        /// or an amount that is worth 0 tokens at the current exchange rate.
+        ZeroAmount,
+
+        /// An account has reached `MaxUnbondingChunks` pending unbonding requests in the pool.
+        TooManyUnbondingChunks,
+
+        /// An account has no unbonding request ready to be withdrawn.
+        NothingToWithdraw,
+
+        /// The asset could not be transferred.
+        TransferFailed,
+
+        /// The derivative could not be minted.
+        MintFailed,
+
+        /// The derivative could not be burned.
+        BurnFailed,
+
+        /// The derivative could not be destroyed.
+        DestroyFailed,
+
+        /// The percentage is above `MaxRewardRate`.
+        PercentageTooHigh,
+
+        /// The commission is above `MaxCommission`.
+        CommissionTooHigh,
+
+        /// The cooldown is out of `MinBlockToUnlock..=MaxBlockToUnlock`.
+        BlockToUnlockOutOfBounds,
+
+        /// The reward period is 0 or above `MaxRewardPeriod`.
+        RewardPeriodOutOfBounds,
+    }
+
+    #[pallet::hooks]
+    impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+        fn integrity_test() {
+            assert!(
+                T::MinBlockToUnlock::get() <= T::MaxBlockToUnlock::get(),
+                "`MinBlockToUnlock` must not be above `MaxBlockToUnlock`"
+            );
+            assert!(
+                !T::MaxRewardPeriod::get().is_zero(),
+                "`MaxRewardPeriod` must allow distributing rewards"
+            );
+            assert!(
+                T::MaxUnbondingChunks::get() > 0,
+                "`MaxUnbondingChunks` must allow unstaking"
+            );
+        }
+    }
+
+    #[pallet::call]
+    impl<T: Config> Pallet<T> {
+        /// Create a pool staking `asset` and minting the new asset `staked_asset`, reserving
+        /// `PoolDeposit` from the caller, who becomes its owner.
+        ///
+        /// Anyone can create a pool, so the derivative is not sufficient: an account must already
+        /// exist, e.g. holding the existential deposit of `Currency`, to receive it.
+        #[pallet::weight(T::WeightInfo::create_pool())]
+        pub fn create_pool(
+            origin: OriginFor<T>,
+            asset: T::AssetId,
+            staked_asset: T::AssetId,
+            percentage: Perbill,
+            commission: Perbill,
+            block_to_unlock: u32,
+            reward_period: T::BlockNumber,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            ensure!(asset != staked_asset, Error::<T>::SameAsset);
+            Self::ensure_valid_parameters(percentage, commission, block_to_unlock, reward_period)?;
+
+            // An asset always has a positive minimum balance, the derivative gets the same one.
+            let min_balance = T::Assets::minimum_balance(asset);
+            ensure!(!min_balance.is_zero(), Error::<T>::UnknownAsset);
+
+            let deposit = T::PoolDeposit::get();
+            T::Currency::reserve(&who, deposit).map_err(|_| Error::<T>::InsufficientDeposit)?;
+
+            let pool_id = NextPoolId::<T>::get();
+            NextPoolId::<T>::put(pool_id.checked_add(1).ok_or(ArithmeticError::Overflow)?);
+
+            let pool_account = Self::pool_account(pool_id);
+            T::Assets::create(staked_asset, pool_account.clone(), false, min_balance)
+                .map_err(|_| Error::<T>::StakedAssetInUse)?;
+
+            // The pool and the pot must exist to hold an asset that is not sufficient.
+            frame_system::Pallet::<T>::inc_providers(&pool_account);
+            frame_system::Pallet::<T>::inc_providers(&Self::pot_account(pool_id));
+
+            Pools::<T>::insert(
+                pool_id,
+                PoolInfo {
+                    owner: who.clone(),
+                    deposit,
+                    asset,
+                    staked_asset,
+                    percentage,
+                    commission,
+                    block_to_unlock,
+                    reward_period,
+                },
+            );
+            LastRewardBlock::<T>::insert(pool_id, <frame_system::Pallet<T>>::block_number());
+            Self::deposit_event(Event::PoolCreated(pool_id, who, asset, staked_asset));
+
+            Ok(())
+        }
+
+        /// Change the parameters of the pool `pool_id`, within the bounds of the pallet.
+        /// The rewards due are distributed with the old parameters first.
+        #[pallet::weight(T::WeightInfo::set_pool_parameters())]
+        pub fn set_pool_parameters(
+            origin: OriginFor<T>,
+            pool_id: PoolId,
+            percentage: Perbill,
+            commission: Perbill,
+            block_to_unlock: u32,
+            reward_period: T::BlockNumber,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let mut pool = Self::pool(pool_id)?;
+            ensure!(pool.owner == who, Error::<T>::NotPoolOwner);
+            Self::ensure_valid_parameters(percentage, commission, block_to_unlock, reward_period)?;
+            Self::distribute_rewards(pool_id, &pool);
+
+            pool.percentage = percentage;
+            pool.commission = commission;
+            pool.block_to_unlock = block_to_unlock;
+            pool.reward_period = reward_period;
+            Pools::<T>::insert(pool_id, pool);
+            Self::deposit_event(Event::PoolParametersSet(
+                pool_id,
+                percentage,
+                commission,
+                block_to_unlock,
+                reward_period,
+            ));
+
+            Ok(())
+        }
+
+        /// Close the pool `pool_id` once its derivative has been redeemed and withdrawn.
+        /// What is left in the pool and in its pot goes to the owner, the derivative is
+        /// destroyed and the deposit is returned.
+        #[pallet::weight(T::WeightInfo::close_pool())]
+        pub fn close_pool(origin: OriginFor<T>, pool_id: PoolId) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let pool = Self::pool(pool_id)?;
+            ensure!(pool.owner == who, Error::<T>::NotPoolOwner);
+            ensure!(
+                T::Assets::total_issuance(pool.staked_asset).is_zero()
+                    && Unbonding::<T>::iter_key_prefix(pool_id).next().is_none(),
+                Error::<T>::PoolNotEmpty
+            );
+
+            // The rounding dust of the pool and the rewards left in the pot.
+            for account in [Self::pool_account(pool_id), Self::pot_account(pool_id)] {
+                let leftover = T::Assets::reducible_balance(pool.asset, &account, false);
+                if !leftover.is_zero() {
+                    T::Assets::transfer(pool.asset, &account, &who, leftover, false)
+                        .map_err(|_| Error::<T>::TransferFailed)?;
+                }
+                // This only fails with `ConsumerRemaining`, when a third party sent another asset
+                // to the account. The account then keeps its provider, and so that asset, instead
+                // of letting the sender block the closing.
+                if frame_system::Pallet::<T>::dec_providers(&account).is_err() {
+                    Self::deposit_event(Event::ProviderKept(pool_id, account));
+                }
+            }
+
+            let witness = T::Assets::get_destroy_witness(&pool.staked_asset)
+                .ok_or(Error::<T>::DestroyFailed)?;
+            T::Assets::destroy(pool.staked_asset, witness, None)
+                .map_err(|_| Error::<T>::DestroyFailed)?;
+
+            Pools::<T>::remove(pool_id);
+            TotalPooled::<T>::remove(pool_id);
+            LastRewardBlock::<T>::remove(pool_id);
+            T::Currency::unreserve(&who, pool.deposit);
+            Self::deposit_event(Event::PoolClosed(pool_id, who));
+
+            Ok(())
+        }
+
+        /// Stake `amount` in the pool `pool_id`, minting its derivative at the current rate.
+        #[pallet::weight(T::WeightInfo::stake())]
+        pub fn stake(
+            origin: OriginFor<T>,
+            pool_id: PoolId,
+            #[pallet::compact] amount: BalanceOf<T>,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let pool = Self::pool(pool_id)?;
+            ensure!(!amount.is_zero(), Error::<T>::ZeroAmount);
+            ensure!(
+                T::Assets::reducible_balance(pool.asset, &who, true) >= amount,
+                Error::<T>::NotEnoughAsset
+            );
+
+            // The rewards due are distributed before pricing the new derivative.
+            Self::distribute_rewards(pool_id, &pool);
+            let minted =
+                Self::asset_to_staked(pool_id, &pool, amount).ok_or(ArithmeticError::Overflow)?;
+            ensure!(!minted.is_zero(), Error::<T>::ZeroAmount);
+            let total_pooled = TotalPooled::<T>::get(pool_id)
+                .checked_add(&amount)
+                .ok_or(ArithmeticError::Overflow)?;
+
+            // Move the asset into the custody of the pool.
+            T::Assets::transfer(pool.asset, &who, &Self::pool_account(pool_id), amount, true)
+                .map_err(|_| Error::<T>::TransferFailed)?;
+            TotalPooled::<T>::insert(pool_id, total_pooled);
+
+            T::Assets::mint_into(pool.staked_asset, &who, minted)
+                .map_err(|_| Error::<T>::MintFailed)?;
+            Self::deposit_event(Event::Staked(pool_id, who, amount, minted));
+
+            Ok(())
+        }
+
+        /// Burn `amount` of the derivative of the pool `pool_id`. The asset it is worth can be
+        /// withdrawn once the cooldown of the pool has passed.
+        #[pallet::weight(T::WeightInfo::unstake())]
+        pub fn unstake(
+            origin: OriginFor<T>,
+            pool_id: PoolId,
+            #[pallet::compact] amount: BalanceOf<T>,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let pool = Self::pool(pool_id)?;
+            ensure!(!amount.is_zero(), Error::<T>::ZeroAmount);
+            ensure!(
+                T::Assets::balance(pool.staked_asset, &who) >= amount,
+                Error::<T>::NotEnoughStakedAsset
+            );
+
+            // The rewards due are distributed before pricing the burned derivative.
+            Self::distribute_rewards(pool_id, &pool);
+            let (pooled, supply) = Self::pool_ratio(pool_id, &pool);
+
+            // The dust left below the minimum balance of the derivative is burned too.
+            let burned = T::Assets::burn_from(pool.staked_asset, &who, amount)
+                .map_err(|_| Error::<T>::BurnFailed)?;
+            let redeemed = mul_div(burned, pooled, supply).ok_or(ArithmeticError::Overflow)?;
+            ensure!(!redeemed.is_zero(), Error::<T>::ZeroAmount);
+
+            // The redeemed asset leaves the pool and stays in custody until `withdraw_unbonded`.
+            let unlock_at = <frame_system::Pallet<T>>::block_number() + pool.block_to_unlock.into();
+            Unbonding::<T>::try_mutate(pool_id, &who, |chunks| {
+                chunks.try_push(UnbondingChunk {
+                    value: redeemed,
+                    unlock_at,
+                })
+            })
+            .map_err(|_| Error::<T>::TooManyUnbondingChunks)?;
+            TotalPooled::<T>::mutate(pool_id, |pooled| *pooled = pooled.saturating_sub(redeemed));
+            Self::deposit_event(Event::Unstaked(pool_id, who, burned, redeemed, unlock_at));
+
+            Ok(())
+        }
+
+        /// Withdraw the unbonding requests of the caller in the pool `pool_id` whose cooldown
+        /// has passed.
+        #[pallet::weight(T::WeightInfo::withdraw_unbonded())]
+        pub fn withdraw_unbonded(origin: OriginFor<T>, pool_id: PoolId) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let pool = Self::pool(pool_id)?;
+            let now = <frame_system::Pallet<T>>::block_number();
+            let mut chunks = Unbonding::<T>::get(pool_id, &who);
+            let mut withdrawn: BalanceOf<T> = Zero::zero();
+            chunks.retain(|chunk| {
+                if chunk.unlock_at <= now {
+                    withdrawn = withdrawn.saturating_add(chunk.value);
+                    false
+                } else {
+                    true
+                }
+            });
+
+            ensure!(!withdrawn.is_zero(), Error::<T>::NothingToWithdraw);
+
+            if chunks.is_empty() {
+                Unbonding::<T>::remove(pool_id, &who);
+            } else {
+                Unbonding::<T>::insert(pool_id, &who, chunks);
+            }
+
+            T::Assets::transfer(
+                pool.asset,
+                &Self::pool_account(pool_id),
+                &who,
+                withdrawn,
+                false,
+            )
+            .map_err(|_| Error::<T>::TransferFailed)?;
+            Self::deposit_event(Event::Withdrawn(pool_id, who, withdrawn));
+
+            Ok(())
+        }
+
+        /// Transfer `amount` of the derivative of the pool `pool_id` to `dest`.
+        #[pallet::weight(T::WeightInfo::transfer())]
+        pub fn transfer(
+            origin: OriginFor<T>,
+            pool_id: PoolId,
+            dest: T::AccountId,
+            #[pallet::compact] amount: BalanceOf<T>,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let pool = Self::pool(pool_id)?;
+            ensure!(who != dest, Error::<T>::TransferToSelf);
+            ensure!(!amount.is_zero(), Error::<T>::ZeroAmount);
+            ensure!(
+                T::Assets::balance(pool.staked_asset, &who) >= amount,
+                Error::<T>::NotEnoughStakedAsset
+            );
+
+            T::Assets::transfer(pool.staked_asset, &who, &dest, amount, false)
+                .map_err(|_| Error::<T>::TransferFailed)?;
+            Self::deposit_event(Event::StakedAssetTransferred(pool_id, who, dest, amount));
+
+            Ok(())
+        }
+
+        /// Fund the pot paying the rewards of the pool `pool_id`.
+        ///
+        /// The funds are not refunded: what is left in the pot when the pool is closed goes to the
+        /// owner of the pool, whoever funded it.
+        #[pallet::weight(T::WeightInfo::fund_pot())]
+        pub fn fund_pot(
+            origin: OriginFor<T>,
+            pool_id: PoolId,
+            #[pallet::compact] amount: BalanceOf<T>,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let pool = Self::pool(pool_id)?;
+            ensure!(!amount.is_zero(), Error::<T>::ZeroAmount);
+            ensure!(
+                T::Assets::reducible_balance(pool.asset, &who, true) >= amount,
+                Error::<T>::NotEnoughAsset
+            );
+
+            T::Assets::transfer(pool.asset, &who, &Self::pot_account(pool_id), amount, true)
+                .map_err(|_| Error::<T>::TransferFailed)?;
+            Self::deposit_event(Event::PotFunded(pool_id, who, amount));
+
+            Ok(())
+        }
+    }
+
+    impl<T: Config> Pallet<T> {
+        /// The pool `pool_id`, or `UnknownPool`.
+        fn pool(pool_id: PoolId) -> Result<PoolInfoOf<T>, Error<T>> {
+            Pools::<T>::get(pool_id).ok_or(Error::<T>::UnknownPool)
+        }
+
+        /// The account holding the asset staked in the pool, and owning its derivative.
+        pub fn pool_account(pool_id: PoolId) -> T::AccountId {
+            T::PalletId::get().into_sub_account_truncating((b"pool", pool_id))
+        }
+
+        /// The account holding the asset paying the rewards of the pool.
+        pub fn pot_account(pool_id: PoolId) -> T::AccountId {
+            T::PalletId::get().into_sub_account_truncating((b"pot", pool_id))
+        }
+
+        /// Check the parameters of a pool against the bounds of the pallet.
+        fn ensure_valid_parameters(
+            percentage: Perbill,
+            commission: Perbill,
+            block_to_unlock: u32,
+            reward_period: T::BlockNumber,
+        ) -> DispatchResult {
+            ensure!(
+                percentage <= T::MaxRewardRate::get(),
+                Error::<T>::PercentageTooHigh
+            );
+            ensure!(
+                commission <= T::MaxCommission::get(),
+                Error::<T>::CommissionTooHigh
+            );
+            ensure!(
+                (T::MinBlockToUnlock::get()..=T::MaxBlockToUnlock::get())
+                    .contains(&block_to_unlock),
+                Error::<T>::BlockToUnlockOutOfBounds
+            );
+            ensure!(
+                !reward_period.is_zero() && reward_period <= T::MaxRewardPeriod::get(),
+                Error::<T>::RewardPeriodOutOfBounds
+            );
+            Ok(())
+        }
+
+        /// Move the rewards of the periods elapsed since the last distribution from the pot
+        /// into the pool, paying the commission to the owner. If the pot can't cover them, it
+        /// pays what it holds above its minimum balance.
+        fn distribute_rewards(pool_id: PoolId, pool: &PoolInfoOf<T>) {
+            let now = <frame_system::Pallet<T>>::block_number();
+            let last = LastRewardBlock::<T>::get(pool_id);
+            let periods = now.saturating_sub(last) / pool.reward_period;
+            if periods.is_zero() {
+                return;
+            }
+            LastRewardBlock::<T>::insert(
+                pool_id,
+                last.saturating_add(periods.saturating_mul(pool.reward_period)),
+            );
+
+            // Without derivative in circulation there is nobody to reward.
+            let pooled = TotalPooled::<T>::get(pool_id);
+            if T::Assets::total_issuance(pool.staked_asset).is_zero() {
+                return;
+            }
+            let periods: u32 = periods.saturated_into();
+            let required = pool
+                .percentage
+                .mul_floor(pooled)
+                .saturating_mul(periods.into());
+            let pot = Self::pot_account(pool_id);
+            let reward = required.min(T::Assets::reducible_balance(pool.asset, &pot, true));
+            if reward.is_zero() {
+                return;
+            }
+
+            // If the owner can't receive the commission, e.g. below the minimum balance of the
+            // asset, it stays with the stakers.
+            let mut commission = pool.commission.mul_floor(reward);
+            if !commission.is_zero()
+                && T::Assets::transfer(pool.asset, &pot, &pool.owner, commission, true).is_err()
+            {
+                commission = Zero::zero();
+            }
+            let pooled_reward = reward.saturating_sub(commission);
+
+            if T::Assets::transfer(
+                pool.asset,
+                &pot,
+                &Self::pool_account(pool_id),
+                pooled_reward,
+                true,
+            )
+            .is_ok()
+            {
+                TotalPooled::<T>::insert(pool_id, pooled.saturating_add(pooled_reward));
+                Self::deposit_event(Event::RewardsDistributed(
+                    pool_id,
+                    pooled_reward,
+                    commission,
+                    required,
+                ));
+            }
+        }
+
+        /// The exchange rate of the pool as a `(pooled, supply)` pair:
+        /// `supply` derivative can be redeemed for `pooled` of the staked asset.
+        fn pool_ratio(pool_id: PoolId, pool: &PoolInfoOf<T>) -> (BalanceOf<T>, BalanceOf<T>) {
+            let pooled = TotalPooled::<T>::get(pool_id);
+            let supply = T::Assets::total_issuance(pool.staked_asset);
+            if pooled.is_zero() || supply.is_zero() {
+                (One::one(), One::one())
+            } else {
+                (pooled, supply)
+            }
+        }
+
+        /// The derivative minted for `amount` of the staked asset, rounded down in favour of
+        /// the pool.
+        fn asset_to_staked(
+            pool_id: PoolId,
+            pool: &PoolInfoOf<T>,
+            amount: BalanceOf<T>,
+        ) -> Option<BalanceOf<T>> {
+            let (pooled, supply) = Self::pool_ratio(pool_id, pool);
+            mul_div(amount, supply, pooled)
+        }
+
+        /// The staked asset a single derivative of the pool `pool_id` can be redeemed for,
+        /// without the rewards not distributed yet. `None` if there is no such pool.
+        pub fn exchange_rate(pool_id: PoolId) -> Option<FixedU128> {
+            let pool = Pools::<T>::get(pool_id)?;
+            let (pooled, supply) = Self::pool_ratio(pool_id, &pool);
+            Some(FixedU128::saturating_from_rational(pooled, supply))
+        }
+    }
+}
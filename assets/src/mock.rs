@@ -0,0 +1,155 @@
+use crate as asset_pools;
+use crate::PoolId;
+use frame_support::traits::tokens::fungibles::{Create, Mutate};
+use frame_support::traits::{ConstU128, ConstU16, ConstU32, ConstU64};
+use frame_support::PalletId;
+use frame_support::{assert_ok, parameter_types};
+use frame_system::EnsureRoot;
+
+use sp_core::H256;
+use sp_runtime::Perbill;
+use sp_runtime::{
+    testing::Header,
+    traits::{BlakeTwo256, IdentityLookup},
+};
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+pub type Balance = u128;
+
+/// The assets staked in the tests, created with a minimum balance of 1.
+pub const DOT: u32 = 1;
+pub const KSM: u32 = 2;
+
+/// The derivatives of `DOT` and `KSM`, created by `create_pool`.
+pub const LDOT: u32 = 11;
+pub const LKSM: u32 = 12;
+
+/// The account creating the pools in the tests. It holds 100 native tokens and no asset.
+pub const OWNER: u64 = 4;
+
+/// The deposit reserved by `create_pool`.
+pub const POOL_DEPOSIT: Balance = 10;
+
+/// The first two pools created, staking `DOT` and `KSM`.
+pub const DOT_POOL: PoolId = 0;
+pub const KSM_POOL: PoolId = 1;
+
+// Configure a mock runtime to test the pallet.
+frame_support::construct_runtime!(
+    pub enum Test where
+        Block = Block,
+        NodeBlock = Block,
+        UncheckedExtrinsic = UncheckedExtrinsic,
+    {
+        System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
+        Balances: pallet_balances::{Pallet, Call, Storage, Config<T>, Event<T>},
+        Assets: pallet_assets::{Pallet, Call, Storage, Event<T>},
+        AssetPools: asset_pools::{Pallet, Call, Storage, Event<T>},
+    }
+);
+
+impl frame_system::Config for Test {
+    type BaseCallFilter = frame_support::traits::Everything;
+    type BlockWeights = ();
+    type BlockLength = ();
+    type Origin = Origin;
+    type Call = Call;
+    type Index = u64;
+    type BlockNumber = u64;
+    type Hash = H256;
+    type Hashing = BlakeTwo256;
+    type AccountId = u64;
+    type Lookup = IdentityLookup<Self::AccountId>;
+    type Header = Header;
+    type Event = Event;
+    type BlockHashCount = ConstU64<258>;
+    type DbWeight = ();
+    type Version = ();
+    type PalletInfo = PalletInfo;
+    type AccountData = pallet_balances::AccountData<Balance>;
+    type OnNewAccount = ();
+    type OnKilledAccount = ();
+    type SystemWeightInfo = ();
+    type SS58Prefix = ConstU16<42>;
+    type OnSetCode = ();
+    type MaxConsumers = frame_support::traits::ConstU32<16>;
+}
+
+impl pallet_balances::Config for Test {
+    type Balance = Balance;
+    type DustRemoval = ();
+    type Event = Event;
+    type ExistentialDeposit = ConstU128<1>;
+    type AccountStore = System;
+    type WeightInfo = ();
+    type MaxLocks = ConstU32<64>;
+    type MaxReserves = ConstU32<64>;
+    type ReserveIdentifier = [u8; 8];
+}
+
+impl pallet_assets::Config for Test {
+    type Event = Event;
+    type Balance = Balance;
+    type AssetId = u32;
+    type Currency = Balances;
+    type ForceOrigin = EnsureRoot<u64>;
+    type AssetDeposit = ConstU128<1>;
+    type AssetAccountDeposit = ConstU128<1>;
+    type MetadataDepositBase = ConstU128<1>;
+    type MetadataDepositPerByte = ConstU128<1>;
+    type ApprovalDeposit = ConstU128<1>;
+    type StringLimit = ConstU32<50>;
+    type Freezer = ();
+    type Extra = ();
+    type WeightInfo = ();
+}
+
+parameter_types! {
+    pub const AssetPoolsId: PalletId = PalletId(*b"assetpol");
+    pub const MaxRewardRate: Perbill = Perbill::from_percent(50);
+    pub const MaxCommission: Perbill = Perbill::from_percent(20);
+}
+
+impl asset_pools::Config for Test {
+    type Event = Event;
+    type AssetId = u32;
+    type Balance = Balance;
+    type Assets = Assets;
+    type Currency = Balances;
+    type PoolDeposit = ConstU128<POOL_DEPOSIT>;
+    type PalletId = AssetPoolsId;
+    type MaxUnbondingChunks = ConstU32<2>;
+    type MinBlockToUnlock = ConstU32<1>;
+    type MaxBlockToUnlock = ConstU32<100>;
+    type MaxRewardRate = MaxRewardRate;
+    type MaxRewardPeriod = ConstU64<100>;
+    type MaxCommission = MaxCommission;
+    type WeightInfo = ();
+}
+
+// Build genesis storage according to the mock runtime: accounts 1 to 4 hold 100 native tokens,
+// accounts 1 to 3 hold 1_000 `DOT` and 1_000 `KSM`, and no pool exists yet.
+pub fn new_test_ext() -> sp_io::TestExternalities {
+    let mut t = frame_system::GenesisConfig::default()
+        .build_storage::<Test>()
+        .unwrap();
+
+    pallet_balances::GenesisConfig::<Test> {
+        balances: (1..=OWNER).map(|who| (who, 100)).collect(),
+    }
+    .assimilate_storage(&mut t)
+    .unwrap();
+
+    let mut ext = sp_io::TestExternalities::new(t);
+    ext.execute_with(|| {
+        System::set_block_number(1);
+        for asset in [DOT, KSM] {
+            assert_ok!(<Assets as Create<u64>>::create(asset, 0, true, 1));
+            for who in 1..=3 {
+                assert_ok!(<Assets as Mutate<u64>>::mint_into(asset, &who, 1_000));
+            }
+        }
+    });
+    ext
+}
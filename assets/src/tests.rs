@@ -0,0 +1,521 @@
+use crate::{mock::*, Error, PoolInfo, UnbondingChunk};
+use frame_support::sp_runtime::traits::Zero;
+use frame_support::sp_runtime::{DispatchError, FixedPointNumber, FixedU128, Perbill};
+use frame_support::traits::tokens::fungibles::{Create, Inspect, Mutate};
+use frame_support::traits::{Currency, ReservableCurrency};
+use frame_support::{assert_noop, assert_ok};
+
+// `OWNER` creates the `DOT` pool: 10% every 5 blocks with a 10% commission and a cooldown of
+// 3 blocks, and the `KSM` pool: 20% every 10 blocks without commission and a cooldown of 10
+// blocks.
+fn create_pools() {
+    assert_ok!(AssetPools::create_pool(
+        Origin::signed(OWNER),
+        DOT,
+        LDOT,
+        Perbill::from_percent(10),
+        Perbill::from_percent(10),
+        3,
+        5
+    ));
+    assert_ok!(AssetPools::create_pool(
+        Origin::signed(OWNER),
+        KSM,
+        LKSM,
+        Perbill::from_percent(20),
+        Perbill::zero(),
+        10,
+        10
+    ));
+}
+
+#[test]
+fn create_pool_works() {
+    new_test_ext().execute_with(|| {
+        create_pools();
+
+        assert_eq!(
+            AssetPools::pools(DOT_POOL),
+            Some(PoolInfo {
+                owner: OWNER,
+                deposit: POOL_DEPOSIT,
+                asset: DOT,
+                staked_asset: LDOT,
+                percentage: Perbill::from_percent(10),
+                commission: Perbill::from_percent(10),
+                block_to_unlock: 3,
+                reward_period: 5,
+            })
+        );
+        assert_eq!(AssetPools::next_pool_id(), 2);
+        assert_eq!(AssetPools::last_reward_block(DOT_POOL), 1);
+        assert_eq!(Balances::reserved_balance(OWNER), 2 * POOL_DEPOSIT);
+        // The derivative gets the minimum balance of the staked asset.
+        assert_eq!(<Assets as Inspect<u64>>::minimum_balance(LDOT), 1);
+        assert_eq!(Assets::total_supply(LDOT), 0);
+        assert_ne!(
+            AssetPools::pool_account(DOT_POOL),
+            AssetPools::pool_account(KSM_POOL)
+        );
+        assert_ne!(
+            AssetPools::pool_account(DOT_POOL),
+            AssetPools::pot_account(DOT_POOL)
+        );
+        System::assert_last_event(Event::AssetPools(crate::Event::PoolCreated(
+            KSM_POOL, OWNER, KSM, LKSM,
+        )));
+
+        // Several pools can stake the same asset.
+        assert_ok!(AssetPools::create_pool(
+            Origin::signed(1),
+            DOT,
+            20,
+            Perbill::from_percent(5),
+            Perbill::zero(),
+            1,
+            1
+        ));
+        assert_eq!(AssetPools::pools(2).unwrap().owner, 1);
+    });
+}
+
+#[test]
+fn create_pool_checks_its_arguments() {
+    new_test_ext().execute_with(|| {
+        let create = |origin,
+                      asset,
+                      staked_asset,
+                      percentage,
+                      commission,
+                      block_to_unlock,
+                      reward_period| {
+            AssetPools::create_pool(
+                origin,
+                asset,
+                staked_asset,
+                Perbill::from_percent(percentage),
+                Perbill::from_percent(commission),
+                block_to_unlock,
+                reward_period,
+            )
+        };
+
+        assert_noop!(
+            create(Origin::root(), DOT, LDOT, 10, 10, 3, 5),
+            DispatchError::BadOrigin
+        );
+        assert_noop!(
+            create(Origin::signed(OWNER), DOT, DOT, 10, 10, 3, 5),
+            Error::<Test>::SameAsset
+        );
+        assert_noop!(
+            create(Origin::signed(OWNER), DOT, LDOT, 60, 10, 3, 5),
+            Error::<Test>::PercentageTooHigh
+        );
+        assert_noop!(
+            create(Origin::signed(OWNER), DOT, LDOT, 10, 30, 3, 5),
+            Error::<Test>::CommissionTooHigh
+        );
+        assert_noop!(
+            create(Origin::signed(OWNER), DOT, LDOT, 10, 10, 0, 5),
+            Error::<Test>::BlockToUnlockOutOfBounds
+        );
+        assert_noop!(
+            create(Origin::signed(OWNER), DOT, LDOT, 10, 10, 3, 0),
+            Error::<Test>::RewardPeriodOutOfBounds
+        );
+        assert_noop!(
+            create(Origin::signed(OWNER), 3, LDOT, 10, 10, 3, 5),
+            Error::<Test>::UnknownAsset
+        );
+        // Account 5 has no native token to reserve.
+        assert_noop!(
+            create(Origin::signed(5), DOT, LDOT, 10, 10, 3, 5),
+            Error::<Test>::InsufficientDeposit
+        );
+        // The derivative must be a new asset.
+        assert_noop!(
+            create(Origin::signed(OWNER), DOT, KSM, 10, 10, 3, 5),
+            Error::<Test>::StakedAssetInUse
+        );
+    });
+}
+
+#[test]
+fn stake_and_unstake_work() {
+    new_test_ext().execute_with(|| {
+        create_pools();
+
+        // An empty pool starts with a 1:1 rate.
+        assert_ok!(AssetPools::stake(Origin::signed(1), DOT_POOL, 100));
+        assert_eq!(Assets::balance(DOT, 1), 900);
+        assert_eq!(Assets::balance(LDOT, 1), 100);
+        assert_eq!(
+            Assets::balance(DOT, AssetPools::pool_account(DOT_POOL)),
+            100
+        );
+        assert_eq!(AssetPools::total_pooled(DOT_POOL), 100);
+        System::assert_last_event(Event::AssetPools(crate::Event::Staked(
+            DOT_POOL, 1, 100, 100,
+        )));
+
+        assert_ok!(AssetPools::unstake(Origin::signed(1), DOT_POOL, 40));
+        assert_eq!(Assets::balance(LDOT, 1), 60);
+        assert_eq!(AssetPools::total_pooled(DOT_POOL), 60);
+        assert_eq!(
+            AssetPools::unbonding(DOT_POOL, 1).into_inner(),
+            vec![UnbondingChunk {
+                value: 40,
+                unlock_at: 4
+            }]
+        );
+        System::assert_last_event(Event::AssetPools(crate::Event::Unstaked(
+            DOT_POOL, 1, 40, 40, 4,
+        )));
+
+        // The redeemed asset stays in custody for the cooldown of the pool.
+        System::set_block_number(3);
+        assert_noop!(
+            AssetPools::withdraw_unbonded(Origin::signed(1), DOT_POOL),
+            Error::<Test>::NothingToWithdraw
+        );
+        System::set_block_number(4);
+        assert_ok!(AssetPools::withdraw_unbonded(Origin::signed(1), DOT_POOL));
+        assert_eq!(Assets::balance(DOT, 1), 940);
+        assert!(AssetPools::unbonding(DOT_POOL, 1).is_empty());
+        System::assert_last_event(Event::AssetPools(crate::Event::Withdrawn(DOT_POOL, 1, 40)));
+    });
+}
+
+#[test]
+fn stake_and_unstake_check_their_arguments() {
+    new_test_ext().execute_with(|| {
+        create_pools();
+
+        assert_noop!(
+            AssetPools::stake(Origin::signed(1), 3, 100),
+            Error::<Test>::UnknownPool
+        );
+        assert_noop!(
+            AssetPools::stake(Origin::signed(1), DOT_POOL, 0),
+            Error::<Test>::ZeroAmount
+        );
+        // The staker keeps the minimum balance of the asset.
+        assert_noop!(
+            AssetPools::stake(Origin::signed(1), DOT_POOL, 1_000),
+            Error::<Test>::NotEnoughAsset
+        );
+
+        assert_ok!(AssetPools::stake(Origin::signed(1), DOT_POOL, 100));
+        assert_noop!(
+            AssetPools::unstake(Origin::signed(1), DOT_POOL, 101),
+            Error::<Test>::NotEnoughStakedAsset
+        );
+        assert_noop!(
+            AssetPools::unstake(Origin::signed(1), KSM_POOL, 1),
+            Error::<Test>::NotEnoughStakedAsset
+        );
+
+        // The unbonding requests of an account are bounded in each pool.
+        assert_ok!(AssetPools::unstake(Origin::signed(1), DOT_POOL, 10));
+        assert_ok!(AssetPools::unstake(Origin::signed(1), DOT_POOL, 10));
+        assert_noop!(
+            AssetPools::unstake(Origin::signed(1), DOT_POOL, 10),
+            Error::<Test>::TooManyUnbondingChunks
+        );
+    });
+}
+
+#[test]
+fn pools_are_independent() {
+    new_test_ext().execute_with(|| {
+        create_pools();
+
+        assert_ok!(AssetPools::stake(Origin::signed(1), DOT_POOL, 100));
+        assert_ok!(AssetPools::stake(Origin::signed(1), KSM_POOL, 200));
+        assert_eq!(Assets::balance(LDOT, 1), 100);
+        assert_eq!(Assets::balance(LKSM, 1), 200);
+        assert_eq!(AssetPools::total_pooled(DOT_POOL), 100);
+        assert_eq!(AssetPools::total_pooled(KSM_POOL), 200);
+
+        // Each pool has its own cooldown.
+        assert_ok!(AssetPools::unstake(Origin::signed(1), DOT_POOL, 50));
+        assert_ok!(AssetPools::unstake(Origin::signed(1), KSM_POOL, 50));
+        assert_eq!(AssetPools::unbonding(DOT_POOL, 1)[0].unlock_at, 4);
+        assert_eq!(AssetPools::unbonding(KSM_POOL, 1)[0].unlock_at, 11);
+
+        System::set_block_number(4);
+        assert_ok!(AssetPools::withdraw_unbonded(Origin::signed(1), DOT_POOL));
+        assert_noop!(
+            AssetPools::withdraw_unbonded(Origin::signed(1), KSM_POOL),
+            Error::<Test>::NothingToWithdraw
+        );
+        assert_eq!(Assets::balance(DOT, 1), 950);
+        assert_eq!(Assets::balance(KSM, 1), 800);
+    });
+}
+
+#[test]
+fn rewards_raise_the_exchange_rate() {
+    new_test_ext().execute_with(|| {
+        create_pools();
+        assert_ok!(AssetPools::stake(Origin::signed(1), DOT_POOL, 100));
+        assert_ok!(AssetPools::fund_pot(Origin::signed(3), DOT_POOL, 50));
+        System::assert_last_event(Event::AssetPools(crate::Event::PotFunded(DOT_POOL, 3, 50)));
+
+        // Two periods elapsed: 10% of the pool twice, minus the 10% commission of the owner.
+        // The rate is only updated when the pool is used, before the new stake is priced.
+        System::set_block_number(11);
+        assert_eq!(
+            AssetPools::exchange_rate(DOT_POOL),
+            Some(FixedU128::saturating_from_integer(1u32))
+        );
+        assert_ok!(AssetPools::stake(Origin::signed(2), DOT_POOL, 118));
+        System::assert_has_event(Event::AssetPools(crate::Event::RewardsDistributed(
+            DOT_POOL, 18, 2, 20,
+        )));
+        assert_eq!(Assets::balance(DOT, OWNER), 2);
+        assert_eq!(Assets::balance(LDOT, 2), 100);
+        assert_eq!(AssetPools::total_pooled(DOT_POOL), 236);
+        assert_eq!(AssetPools::last_reward_block(DOT_POOL), 11);
+        assert_eq!(Assets::balance(DOT, AssetPools::pot_account(DOT_POOL)), 30);
+        assert_eq!(
+            AssetPools::exchange_rate(DOT_POOL),
+            Some(FixedU128::saturating_from_rational(59, 50))
+        );
+
+        // The first staker redeems its share of the rewards.
+        assert_ok!(AssetPools::unstake(Origin::signed(1), DOT_POOL, 100));
+        assert_eq!(AssetPools::unbonding(DOT_POOL, 1)[0].value, 118);
+        assert_eq!(AssetPools::total_pooled(DOT_POOL), 118);
+
+        // The other pools are not rewarded by this pot.
+        assert_eq!(AssetPools::total_pooled(KSM_POOL), 0);
+        assert_eq!(AssetPools::exchange_rate(3), None);
+    });
+}
+
+#[test]
+fn short_pot_pays_what_it_holds() {
+    new_test_ext().execute_with(|| {
+        create_pools();
+        assert_ok!(AssetPools::stake(Origin::signed(1), DOT_POOL, 100));
+        // The pot keeps the minimum balance of the asset.
+        assert_ok!(AssetPools::fund_pot(Origin::signed(3), DOT_POOL, 6));
+
+        // The commission of 5 rounds down to 0.
+        System::set_block_number(11);
+        assert_ok!(AssetPools::stake(Origin::signed(2), DOT_POOL, 105));
+        System::assert_has_event(Event::AssetPools(crate::Event::RewardsDistributed(
+            DOT_POOL, 5, 0, 20,
+        )));
+        assert_eq!(Assets::balance(LDOT, 2), 100);
+        assert_eq!(AssetPools::total_pooled(DOT_POOL), 210);
+        assert_eq!(Assets::balance(DOT, AssetPools::pot_account(DOT_POOL)), 1);
+    });
+}
+
+#[test]
+fn empty_pool_is_not_rewarded() {
+    new_test_ext().execute_with(|| {
+        create_pools();
+        assert_ok!(AssetPools::fund_pot(Origin::signed(3), DOT_POOL, 50));
+
+        // The periods elapsed without stakers are skipped.
+        System::set_block_number(11);
+        assert_ok!(AssetPools::stake(Origin::signed(1), DOT_POOL, 100));
+        assert_eq!(Assets::balance(LDOT, 1), 100);
+        assert_eq!(AssetPools::last_reward_block(DOT_POOL), 11);
+        assert_eq!(Assets::balance(DOT, AssetPools::pot_account(DOT_POOL)), 50);
+
+        System::set_block_number(16);
+        assert_ok!(AssetPools::unstake(Origin::signed(1), DOT_POOL, 100));
+        assert_eq!(AssetPools::unbonding(DOT_POOL, 1)[0].value, 109);
+        assert_eq!(Assets::balance(DOT, OWNER), 1);
+    });
+}
+
+#[test]
+fn transfer_works() {
+    new_test_ext().execute_with(|| {
+        create_pools();
+        assert_ok!(AssetPools::stake(Origin::signed(1), DOT_POOL, 100));
+
+        assert_ok!(AssetPools::transfer(Origin::signed(1), DOT_POOL, 2, 30));
+        assert_eq!(Assets::balance(LDOT, 1), 70);
+        assert_eq!(Assets::balance(LDOT, 2), 30);
+        System::assert_last_event(Event::AssetPools(crate::Event::StakedAssetTransferred(
+            DOT_POOL, 1, 2, 30,
+        )));
+
+        assert_noop!(
+            AssetPools::transfer(Origin::signed(1), DOT_POOL, 1, 30),
+            Error::<Test>::TransferToSelf
+        );
+        assert_noop!(
+            AssetPools::transfer(Origin::signed(1), DOT_POOL, 2, 71),
+            Error::<Test>::NotEnoughStakedAsset
+        );
+        assert_noop!(
+            AssetPools::transfer(Origin::signed(1), 3, 2, 1),
+            Error::<Test>::UnknownPool
+        );
+
+        // The receiver can redeem it.
+        assert_ok!(AssetPools::unstake(Origin::signed(2), DOT_POOL, 30));
+        assert_eq!(AssetPools::unbonding(DOT_POOL, 2)[0].value, 30);
+    });
+}
+
+#[test]
+fn staked_asset_is_not_sufficient() {
+    new_test_ext().execute_with(|| {
+        create_pools();
+        assert_ok!(AssetPools::stake(Origin::signed(1), DOT_POOL, 100));
+
+        // Account 5 holds no native token, so it can't be created by the derivative alone.
+        assert_eq!(Balances::free_balance(5), 0);
+        assert_noop!(
+            AssetPools::transfer(Origin::signed(1), DOT_POOL, 5, 30),
+            Error::<Test>::TransferFailed
+        );
+
+        assert_ok!(Balances::transfer(Origin::signed(4), 5, 10));
+        assert_ok!(AssetPools::transfer(Origin::signed(1), DOT_POOL, 5, 30));
+        assert_eq!(Assets::balance(LDOT, 5), 30);
+    });
+}
+
+#[test]
+fn set_pool_parameters_works() {
+    new_test_ext().execute_with(|| {
+        create_pools();
+        assert_ok!(AssetPools::stake(Origin::signed(1), DOT_POOL, 100));
+        assert_ok!(AssetPools::fund_pot(Origin::signed(3), DOT_POOL, 50));
+
+        let set = |origin, pool_id, commission, block_to_unlock| {
+            AssetPools::set_pool_parameters(
+                origin,
+                pool_id,
+                Perbill::from_percent(20),
+                Perbill::from_percent(commission),
+                block_to_unlock,
+                10,
+            )
+        };
+
+        assert_noop!(
+            set(Origin::signed(1), DOT_POOL, 5, 5),
+            Error::<Test>::NotPoolOwner
+        );
+        assert_noop!(
+            set(Origin::signed(OWNER), 3, 5, 5),
+            Error::<Test>::UnknownPool
+        );
+        assert_noop!(
+            set(Origin::signed(OWNER), DOT_POOL, 5, 101),
+            Error::<Test>::BlockToUnlockOutOfBounds
+        );
+        assert_noop!(
+            set(Origin::signed(OWNER), DOT_POOL, 30, 5),
+            Error::<Test>::CommissionTooHigh
+        );
+
+        // The rewards due are distributed with the old parameters.
+        System::set_block_number(11);
+        assert_ok!(set(Origin::signed(OWNER), DOT_POOL, 5, 5));
+        System::assert_has_event(Event::AssetPools(crate::Event::RewardsDistributed(
+            DOT_POOL, 18, 2, 20,
+        )));
+        System::assert_last_event(Event::AssetPools(crate::Event::PoolParametersSet(
+            DOT_POOL,
+            Perbill::from_percent(20),
+            Perbill::from_percent(5),
+            5,
+            10,
+        )));
+        assert_eq!(AssetPools::pools(DOT_POOL).unwrap().block_to_unlock, 5);
+        assert_eq!(AssetPools::pools(KSM_POOL).unwrap().block_to_unlock, 10);
+
+        // The new cooldown applies to the next requests.
+        assert_ok!(AssetPools::unstake(Origin::signed(1), DOT_POOL, 10));
+        assert_eq!(AssetPools::unbonding(DOT_POOL, 1)[0].unlock_at, 16);
+    });
+}
+
+#[test]
+fn close_pool_works() {
+    new_test_ext().execute_with(|| {
+        create_pools();
+        assert_ok!(AssetPools::stake(Origin::signed(1), DOT_POOL, 100));
+        assert_ok!(AssetPools::fund_pot(Origin::signed(3), DOT_POOL, 50));
+
+        assert_noop!(
+            AssetPools::close_pool(Origin::signed(1), DOT_POOL),
+            Error::<Test>::NotPoolOwner
+        );
+        // The derivative is still in circulation.
+        assert_noop!(
+            AssetPools::close_pool(Origin::signed(OWNER), DOT_POOL),
+            Error::<Test>::PoolNotEmpty
+        );
+        // The redeemed asset is still waiting for its cooldown.
+        assert_ok!(AssetPools::unstake(Origin::signed(1), DOT_POOL, 100));
+        assert_noop!(
+            AssetPools::close_pool(Origin::signed(OWNER), DOT_POOL),
+            Error::<Test>::PoolNotEmpty
+        );
+
+        System::set_block_number(4);
+        assert_ok!(AssetPools::withdraw_unbonded(Origin::signed(1), DOT_POOL));
+        assert_ok!(AssetPools::close_pool(Origin::signed(OWNER), DOT_POOL));
+        System::assert_last_event(Event::AssetPools(crate::Event::PoolClosed(DOT_POOL, OWNER)));
+
+        // The pot goes to the owner with the deposit, and the derivative is destroyed.
+        assert_eq!(Assets::balance(DOT, OWNER), 50);
+        assert_eq!(Assets::balance(DOT, AssetPools::pot_account(DOT_POOL)), 0);
+        assert_eq!(Balances::reserved_balance(OWNER), POOL_DEPOSIT);
+        assert_eq!(AssetPools::pools(DOT_POOL), None);
+        assert_eq!(<Assets as Inspect<u64>>::minimum_balance(LDOT), 0);
+        assert_noop!(
+            AssetPools::stake(Origin::signed(1), DOT_POOL, 100),
+            Error::<Test>::UnknownPool
+        );
+
+        // The derivative can be reused by a new pool.
+        assert_ok!(AssetPools::create_pool(
+            Origin::signed(OWNER),
+            DOT,
+            LDOT,
+            Perbill::from_percent(10),
+            Perbill::zero(),
+            3,
+            5
+        ));
+        assert_eq!(AssetPools::pools(2).unwrap().staked_asset, LDOT);
+    });
+}
+
+#[test]
+fn close_pool_keeps_the_provider_of_an_account_holding_other_assets() {
+    new_test_ext().execute_with(|| {
+        create_pools();
+        let pool_account = AssetPools::pool_account(DOT_POOL);
+        let pot_account = AssetPools::pot_account(DOT_POOL);
+
+        // A third party sends an asset that is not sufficient to the pool account.
+        assert_ok!(<Assets as Create<u64>>::create(7, 0, false, 1));
+        assert_ok!(<Assets as Mutate<u64>>::mint_into(7, &pool_account, 5));
+
+        assert_ok!(AssetPools::close_pool(Origin::signed(OWNER), DOT_POOL));
+        System::assert_has_event(Event::AssetPools(crate::Event::ProviderKept(
+            DOT_POOL,
+            pool_account,
+        )));
+        System::assert_last_event(Event::AssetPools(crate::Event::PoolClosed(DOT_POOL, OWNER)));
+
+        assert_eq!(System::providers(&pool_account), 1);
+        assert_eq!(Assets::balance(7, pool_account), 5);
+        assert_eq!(System::providers(&pot_account), 0);
+    });
+}
@@ -0,0 +1,161 @@
+//! Weights for the asset pools pallet.
+//!
+//! The values below are placeholders, not benchmark results: the storage accesses are counted by
+//! hand from the worst path of each call, with `pallet-assets` as `Config::Assets`, and the
+//! execution times are rough estimates. Regenerate them from the benchmarks in `benchmarking.rs`
+//! before using the pallet in production, with:
+//!
+//! ```text
+//! ./target/release/node-template benchmark pallet \
+//!     --chain=dev --steps=50 --repeat=20 \
+//!     --pallet=simple_pool_assets --extrinsic='*' \
+//!     --execution=wasm --wasm-execution=compiled \
+//!     --output=./assets/src/weights.rs --template=./.maintain/frame-weight-template.hbs
+//! ```
+
+#![cfg_attr(rustfmt, rustfmt_skip)]
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use core::marker::PhantomData;
+use frame_support::{traits::Get, weights::{Weight, constants::RocksDbWeight}};
+
+/// Weight functions needed for the asset pools pallet.
+pub trait WeightInfo {
+	fn create_pool() -> Weight;
+	fn set_pool_parameters() -> Weight;
+	fn close_pool() -> Weight;
+	fn stake() -> Weight;
+	fn unstake() -> Weight;
+	fn withdraw_unbonded() -> Weight;
+	fn transfer() -> Weight;
+	fn fund_pot() -> Weight;
+}
+
+/// Weights for the asset pools pallet using the Substrate node and recommended hardware.
+pub struct SubstrateWeight<T>(PhantomData<T>);
+impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
+	// Storage: AssetPools NextPoolId (r:1 w:1)
+	// Storage: System Account (r:3 w:3)
+	// Storage: Assets Asset (r:2 w:1)
+	// Storage: AssetPools Pools (r:0 w:1)
+	// Storage: AssetPools LastRewardBlock (r:0 w:1)
+	fn create_pool() -> Weight {
+		(50_000_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(6 as Weight))
+			.saturating_add(T::DbWeight::get().writes(7 as Weight))
+	}
+	// Storage: AssetPools Pools (r:1 w:1)
+	// Storage: AssetPools LastRewardBlock (r:1 w:1)
+	// Storage: AssetPools TotalPooled (r:1 w:1)
+	// Storage: Assets Asset (r:2 w:1)
+	// Storage: Assets Account (r:3 w:3)
+	fn set_pool_parameters() -> Weight {
+		(50_000_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(8 as Weight))
+			.saturating_add(T::DbWeight::get().writes(7 as Weight))
+	}
+	// Storage: AssetPools Pools (r:1 w:1)
+	// Storage: AssetPools Unbonding (r:1 w:0)
+	// Storage: Assets Asset (r:2 w:2)
+	// Storage: Assets Account (r:4 w:4)
+	// Storage: Assets Metadata (r:0 w:1)
+	// Storage: System Account (r:3 w:3)
+	// Storage: AssetPools TotalPooled (r:0 w:1)
+	// Storage: AssetPools LastRewardBlock (r:0 w:1)
+	fn close_pool() -> Weight {
+		(70_000_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(11 as Weight))
+			.saturating_add(T::DbWeight::get().writes(14 as Weight))
+	}
+	// Storage: AssetPools Pools (r:1 w:0)
+	// Storage: AssetPools LastRewardBlock (r:1 w:1)
+	// Storage: AssetPools TotalPooled (r:1 w:1)
+	// Storage: Assets Asset (r:2 w:2)
+	// Storage: Assets Account (r:5 w:5)
+	fn stake() -> Weight {
+		(85_000_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(10 as Weight))
+			.saturating_add(T::DbWeight::get().writes(9 as Weight))
+	}
+	// Storage: AssetPools Pools (r:1 w:0)
+	// Storage: AssetPools LastRewardBlock (r:1 w:1)
+	// Storage: AssetPools TotalPooled (r:1 w:1)
+	// Storage: AssetPools Unbonding (r:1 w:1)
+	// Storage: Assets Asset (r:2 w:2)
+	// Storage: Assets Account (r:4 w:4)
+	fn unstake() -> Weight {
+		(80_000_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(10 as Weight))
+			.saturating_add(T::DbWeight::get().writes(9 as Weight))
+	}
+	// Storage: AssetPools Pools (r:1 w:0)
+	// Storage: AssetPools Unbonding (r:1 w:1)
+	// Storage: Assets Asset (r:1 w:1)
+	// Storage: Assets Account (r:2 w:2)
+	fn withdraw_unbonded() -> Weight {
+		(40_000_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(5 as Weight))
+			.saturating_add(T::DbWeight::get().writes(4 as Weight))
+	}
+	// Storage: AssetPools Pools (r:1 w:0)
+	// Storage: Assets Asset (r:1 w:1)
+	// Storage: Assets Account (r:2 w:2)
+	fn transfer() -> Weight {
+		(40_000_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(4 as Weight))
+			.saturating_add(T::DbWeight::get().writes(3 as Weight))
+	}
+	// Storage: AssetPools Pools (r:1 w:0)
+	// Storage: Assets Asset (r:1 w:1)
+	// Storage: Assets Account (r:2 w:2)
+	fn fund_pot() -> Weight {
+		(40_000_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(4 as Weight))
+			.saturating_add(T::DbWeight::get().writes(3 as Weight))
+	}
+}
+
+// For backwards compatibility and tests
+impl WeightInfo for () {
+	fn create_pool() -> Weight {
+		(50_000_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(6 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(7 as Weight))
+	}
+	fn set_pool_parameters() -> Weight {
+		(50_000_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(8 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(7 as Weight))
+	}
+	fn close_pool() -> Weight {
+		(70_000_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(11 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(14 as Weight))
+	}
+	fn stake() -> Weight {
+		(85_000_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(10 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(9 as Weight))
+	}
+	fn unstake() -> Weight {
+		(80_000_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(10 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(9 as Weight))
+	}
+	fn withdraw_unbonded() -> Weight {
+		(40_000_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(5 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(4 as Weight))
+	}
+	fn transfer() -> Weight {
+		(40_000_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(4 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(3 as Weight))
+	}
+	fn fund_pot() -> Weight {
+		(40_000_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(4 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(3 as Weight))
+	}
+}
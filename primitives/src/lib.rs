@@ -0,0 +1,41 @@
+//! Types and maths shared by the simple pool pallets.
+//!
+//! Both `lrazovic_pallet` and `simple-pool-assets` convert between the pooled token and its
+//! derivative and queue withdrawals behind a cooldown. Keeping these here makes sure the two
+//! pallets round the same way and store the same unbonding chunks.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::{Decode, Encode, MaxEncodedLen};
+use scale_info::TypeInfo;
+use sp_runtime::helpers_128bit::multiply_by_rational;
+use sp_runtime::traits::{AtLeast32BitUnsigned, SaturatedConversion};
+use sp_runtime::RuntimeDebug;
+
+/// `a * b / c` rounded down, computed without intermediate overflow.
+/// `None` if `c` is zero or the result doesn't fit in `B`.
+pub fn mul_div<B: AtLeast32BitUnsigned + Copy>(a: B, b: B, c: B) -> Option<B> {
+    let result =
+        multiply_by_rational(a.saturated_into(), b.saturated_into(), c.saturated_into()).ok()?;
+    result.try_into().ok()
+}
+
+/// `a * b / c` rounded up, computed without intermediate overflow.
+/// `None` if `c` is zero or the result doesn't fit in `B`.
+pub fn mul_div_up<B: AtLeast32BitUnsigned + Copy>(a: B, b: B, c: B) -> Option<B> {
+    let down = mul_div(a, b, c)?;
+    // `down * c >= a * b` exactly when `down * c / b`, rounded down, is at least `a`.
+    match mul_div(down, c, b) {
+        Some(back) if back < a => down.checked_add(&B::one()),
+        _ => Some(down),
+    }
+}
+
+/// A pending withdrawal created by `unstake`, released once its cooldown is over.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct UnbondingChunk<Balance, BlockNumber> {
+    /// The amount to release.
+    pub value: Balance,
+    /// The block from which the chunk can be withdrawn.
+    pub unlock_at: BlockNumber,
+}
@@ -0,0 +1,353 @@
+//! RPC interface of the simple pool pallet.
+//!
+//! Every method forwards to the `SimplePoolApi` runtime API at the given block, or at the best
+//! block if none is given. Balances are serialised as decimal strings, since they don't fit in
+//! the numbers of JavaScript.
+
+use std::{marker::PhantomData, str::FromStr, sync::Arc};
+
+use codec::Codec;
+use jsonrpsee::{
+    core::{Error as JsonRpseeError, RpcResult},
+    proc_macros::rpc,
+    types::error::{CallError, ErrorObject},
+};
+use serde::{Deserialize, Serialize};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::{
+    generic::BlockId,
+    traits::{Block as BlockT, NumberFor},
+    FixedPointNumber, FixedU128,
+};
+
+use simple_pool_runtime_api::Position;
+pub use simple_pool_runtime_api::SimplePoolApi as SimplePoolRuntimeApi;
+
+#[rpc(client, server)]
+pub trait SimplePoolApi<BlockHash, AccountId> {
+    /// The MainToken a single StakedToken can be redeemed for, as a decimal string.
+    #[method(name = "simplePool_exchangeRate")]
+    fn exchange_rate(&self, at: Option<BlockHash>) -> RpcResult<String>;
+
+    /// The StakedToken rewards `who` can claim.
+    #[method(name = "simplePool_pendingRewards")]
+    fn pending_rewards(&self, who: AccountId, at: Option<BlockHash>) -> RpcResult<String>;
+
+    /// The staking position of `who`.
+    #[method(name = "simplePool_position")]
+    fn position(&self, who: AccountId, at: Option<BlockHash>) -> RpcResult<PositionInfo>;
+
+    /// The StakedToken minted by staking `amount` MainToken, `null` on overflow.
+    #[method(name = "simplePool_previewStake")]
+    fn preview_stake(&self, amount: String, at: Option<BlockHash>) -> RpcResult<Option<String>>;
+}
+
+/// The staking position of an account, with the balances as decimal strings.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PositionInfo {
+    /// The StakedToken owned by the account.
+    pub staked: String,
+    /// The MainToken `staked` can be redeemed for at the current exchange rate.
+    pub value: String,
+    /// The MainToken staked by the account and not yet unstaked.
+    pub principal: String,
+    /// The StakedToken still in its cooldown.
+    pub locked: String,
+    /// The StakedToken rewards the account can claim.
+    pub pending_rewards: String,
+}
+
+impl<Balance: ToString> From<Position<Balance>> for PositionInfo {
+    fn from(position: Position<Balance>) -> Self {
+        Self {
+            staked: position.staked.to_string(),
+            value: position.value.to_string(),
+            principal: position.principal.to_string(),
+            locked: position.locked.to_string(),
+            pending_rewards: position.pending_rewards.to_string(),
+        }
+    }
+}
+
+/// Error type of this RPC api.
+pub enum Error {
+    /// The call to the runtime failed.
+    RuntimeError,
+    /// The amount is not a valid balance.
+    InvalidAmount,
+}
+
+impl From<Error> for i32 {
+    fn from(e: Error) -> i32 {
+        match e {
+            Error::RuntimeError => 1,
+            Error::InvalidAmount => 2,
+        }
+    }
+}
+
+fn runtime_error(message: &str, e: impl ToString) -> JsonRpseeError {
+    CallError::Custom(ErrorObject::owned(
+        Error::RuntimeError.into(),
+        message,
+        Some(e.to_string()),
+    ))
+    .into()
+}
+
+/// Format `rate` as a decimal string with all its digits, e.g. `1.500000000000000000`.
+fn format_rate(rate: FixedU128) -> String {
+    let inner = rate.into_inner();
+    let div = FixedU128::DIV;
+    format!("{}.{:018}", inner / div, inner % div)
+}
+
+/// Provides the `SimplePoolApi` RPC methods.
+pub struct SimplePool<C, Block, Balance> {
+    client: Arc<C>,
+    _marker: PhantomData<(Block, Balance)>,
+}
+
+impl<C, Block, Balance> SimplePool<C, Block, Balance> {
+    /// Create new `SimplePool` with the given reference to the client.
+    pub fn new(client: Arc<C>) -> Self {
+        Self {
+            client,
+            _marker: Default::default(),
+        }
+    }
+}
+
+impl<C, Block, Balance> SimplePool<C, Block, Balance>
+where
+    Block: BlockT,
+    C: HeaderBackend<Block>,
+{
+    fn block_id(&self, at: Option<<Block as BlockT>::Hash>) -> BlockId<Block> {
+        BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash))
+    }
+}
+
+impl<C, Block, AccountId, Balance> SimplePoolApiServer<<Block as BlockT>::Hash, AccountId>
+    for SimplePool<C, Block, Balance>
+where
+    Block: BlockT,
+    C: ProvideRuntimeApi<Block> + HeaderBackend<Block> + Send + Sync + 'static,
+    C::Api: SimplePoolRuntimeApi<Block, AccountId, Balance, NumberFor<Block>>,
+    AccountId: Codec,
+    Balance: Codec + ToString + FromStr + Send + Sync + 'static,
+{
+    fn exchange_rate(&self, at: Option<<Block as BlockT>::Hash>) -> RpcResult<String> {
+        let api = self.client.runtime_api();
+        let at = self.block_id(at);
+
+        api.exchange_rate(&at)
+            .map(format_rate)
+            .map_err(|e| runtime_error("Unable to query the exchange rate.", e))
+    }
+
+    fn pending_rewards(
+        &self,
+        who: AccountId,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> RpcResult<String> {
+        let api = self.client.runtime_api();
+        let at = self.block_id(at);
+
+        api.pending_rewards(&at, who)
+            .map(|rewards| rewards.to_string())
+            .map_err(|e| runtime_error("Unable to query the pending rewards.", e))
+    }
+
+    fn position(
+        &self,
+        who: AccountId,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> RpcResult<PositionInfo> {
+        let api = self.client.runtime_api();
+        let at = self.block_id(at);
+
+        api.position(&at, who)
+            .map(PositionInfo::from)
+            .map_err(|e| runtime_error("Unable to query the position.", e))
+    }
+
+    fn preview_stake(
+        &self,
+        amount: String,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> RpcResult<Option<String>> {
+        let api = self.client.runtime_api();
+        let at = self.block_id(at);
+
+        let amount = Balance::from_str(&amount).map_err(|_| {
+            JsonRpseeError::from(CallError::Custom(ErrorObject::owned(
+                Error::InvalidAmount.into(),
+                "Invalid amount.",
+                Some(amount.clone()),
+            )))
+        })?;
+
+        api.preview_stake(&at, amount)
+            .map(|minted| minted.map(|minted| minted.to_string()))
+            .map_err(|e| runtime_error("Unable to preview the stake.", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use simple_pool_runtime_api::PoolTotals;
+    use sp_api::ApiRef;
+    use sp_blockchain::{BlockStatus, Info};
+    use substrate_test_runtime_client::runtime::{Block, Hash};
+
+    struct TestApi;
+
+    struct MockApi;
+
+    impl ProvideRuntimeApi<Block> for TestApi {
+        type Api = MockApi;
+
+        fn runtime_api(&self) -> ApiRef<'_, Self::Api> {
+            MockApi.into()
+        }
+    }
+
+    impl HeaderBackend<Block> for TestApi {
+        fn header(
+            &self,
+            _id: BlockId<Block>,
+        ) -> sp_blockchain::Result<Option<<Block as BlockT>::Header>> {
+            Ok(None)
+        }
+
+        fn info(&self) -> Info<Block> {
+            Info {
+                best_hash: Hash::default(),
+                best_number: 0,
+                genesis_hash: Hash::default(),
+                finalized_hash: Hash::default(),
+                finalized_number: 0,
+                finalized_state: None,
+                number_leaves: 0,
+                block_gap: None,
+            }
+        }
+
+        fn status(&self, _id: BlockId<Block>) -> sp_blockchain::Result<BlockStatus> {
+            Ok(BlockStatus::Unknown)
+        }
+
+        fn number(&self, _hash: Hash) -> sp_blockchain::Result<Option<NumberFor<Block>>> {
+            Ok(None)
+        }
+
+        fn hash(&self, _number: NumberFor<Block>) -> sp_blockchain::Result<Option<Hash>> {
+            Ok(None)
+        }
+    }
+
+    sp_api::mock_impl_runtime_apis! {
+        impl SimplePoolRuntimeApi<Block, u64, u128, NumberFor<Block>> for MockApi {
+            fn exchange_rate() -> FixedU128 {
+                FixedU128::saturating_from_rational(3, 2)
+            }
+
+            fn preview_stake(amount: u128) -> Option<u128> {
+                amount.checked_mul(2).map(|amount| amount / 3)
+            }
+
+            fn preview_unstake(amount: u128) -> Option<u128> {
+                amount.checked_mul(3).map(|amount| amount / 2)
+            }
+
+            fn pending_rewards(_who: u64) -> u128 {
+                u128::MAX
+            }
+
+            fn position(_who: u64) -> Position<u128> {
+                Position {
+                    staked: 200,
+                    value: 300,
+                    principal: 250,
+                    locked: 100,
+                    pending_rewards: u128::MAX,
+                }
+            }
+
+            fn next_unlock_block(_who: u64) -> Option<NumberFor<Block>> {
+                None
+            }
+
+            fn pool_totals() -> PoolTotals<u128> {
+                PoolTotals {
+                    total_pooled: 300,
+                    staked_supply: 200,
+                    total_shares: 200,
+                    pot: 1_000,
+                    owed_rewards: 0,
+                }
+            }
+        }
+    }
+
+    fn rpc() -> SimplePool<TestApi, Block, u128> {
+        SimplePool::new(Arc::new(TestApi))
+    }
+
+    #[test]
+    fn exchange_rate_is_a_decimal_string() {
+        let rate = rpc().exchange_rate(Some(Hash::default())).unwrap();
+        assert_eq!(rate, "1.500000000000000000");
+    }
+
+    #[test]
+    fn queries_the_best_block_by_default() {
+        assert_eq!(rpc().block_id(None), BlockId::hash(Hash::default()));
+
+        let rate = rpc().exchange_rate(None).unwrap();
+        assert_eq!(rate, "1.500000000000000000");
+
+        let position = rpc().position(1, None).unwrap();
+        assert_eq!(position.staked, "200");
+    }
+
+    #[test]
+    fn balances_are_strings() {
+        let rewards = rpc().pending_rewards(1, Some(Hash::default())).unwrap();
+        assert_eq!(rewards, u128::MAX.to_string());
+
+        let position = rpc().position(1, Some(Hash::default())).unwrap();
+        assert_eq!(
+            serde_json::to_value(position).unwrap(),
+            serde_json::json!({
+                "staked": "200",
+                "value": "300",
+                "principal": "250",
+                "locked": "100",
+                "pendingRewards": "340282366920938463463374607431768211455",
+            })
+        );
+    }
+
+    #[test]
+    fn preview_stake_works() {
+        let minted = rpc()
+            .preview_stake("300".into(), Some(Hash::default()))
+            .unwrap();
+        assert_eq!(minted, Some("200".into()));
+
+        let overflow = rpc()
+            .preview_stake(u128::MAX.to_string(), Some(Hash::default()))
+            .unwrap();
+        assert_eq!(overflow, None);
+
+        assert!(rpc()
+            .preview_stake("1.5".into(), Some(Hash::default()))
+            .is_err());
+    }
+}
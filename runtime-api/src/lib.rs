@@ -0,0 +1,40 @@
+//! Runtime API of the simple pool pallet.
+//!
+//! Front-ends can read the state of the pool through these calls instead of decoding its storage
+//! and re-implementing the maths of `stake` and `unstake`.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::Codec;
+pub use lrazovic_pallet::{PoolTotals, Position};
+use sp_runtime::FixedU128;
+
+sp_api::decl_runtime_apis! {
+    pub trait SimplePoolApi<AccountId, Balance, BlockNumber>
+    where
+        AccountId: Codec,
+        Balance: Codec,
+        BlockNumber: Codec,
+    {
+        /// The MainToken a single StakedToken can be redeemed for.
+        fn exchange_rate() -> FixedU128;
+
+        /// The StakedToken minted by staking `amount` MainToken now.
+        fn preview_stake(amount: Balance) -> Option<Balance>;
+
+        /// The MainToken queued for unbonding by unstaking `amount` StakedToken now.
+        fn preview_unstake(amount: Balance) -> Option<Balance>;
+
+        /// The StakedToken rewards `who` can claim.
+        fn pending_rewards(who: AccountId) -> Balance;
+
+        /// The staking position of `who`.
+        fn position(who: AccountId) -> Position<Balance>;
+
+        /// The first block in which a deposit of `who` still in its cooldown unlocks.
+        fn next_unlock_block(who: AccountId) -> Option<BlockNumber>;
+
+        /// The totals of the pool and of its reward pot.
+        fn pool_totals() -> PoolTotals<Balance>;
+    }
+}
@@ -1,20 +1,349 @@
-// //! Benchmarking setup for pallet-template
-
-// use super::*;
-
-// #[allow(unused)]
-// use crate::Pallet as Template;
-// use frame_benchmarking::{benchmarks, whitelisted_caller};
-// use frame_system::RawOrigin;
-
-// benchmarks! {
-//     do_something {
-//         let s in 0 .. 100;
-//         let caller: T::AccountId = whitelisted_caller();
-//     }: _(RawOrigin::Signed(caller), s)
-//     verify {
-//         assert_eq!(Something::<T>::get(), Some(s));
-//     }
-
-//     impl_benchmark_test_suite!(Template, crate::mock::new_test_ext(), crate::mock::Test);
-// }
+//! Benchmarking setup for the simple pool pallet
+
+use super::*;
+
+#[allow(unused)]
+use crate::Pallet as SimplePool;
+use frame_benchmarking::{account, benchmarks, whitelisted_caller};
+use frame_support::sp_runtime::traits::{Bounded, One, Saturating};
+use frame_support::sp_std::vec::Vec;
+use frame_support::traits::{Currency, EnsureOrigin, Get, Hooks};
+use frame_system::RawOrigin;
+
+const SEED: u32 = 0;
+
+fn assert_last_event<T: Config>(generic_event: <T as Config>::Event) {
+    frame_system::Pallet::<T>::assert_last_event(generic_event.into());
+}
+
+// A stake above the existential deposit and the minimum stake.
+fn stake_amount<T: Config>() -> BalanceOf<T> {
+    T::MainToken::minimum_balance()
+        .max(MinStake::<T>::get())
+        .max(1u32.into())
+        .saturating_mul(10u32.into())
+}
+
+// Endow the pool account and fill the reward pot.
+fn setup_pool<T: Config>() {
+    T::MainToken::make_free_balance_be(
+        &SimplePool::<T>::pool_account(),
+        T::MainToken::minimum_balance(),
+    );
+    T::MainToken::make_free_balance_be(
+        &SimplePool::<T>::account_id(),
+        stake_amount::<T>().saturating_mul(1_000u32.into()),
+    );
+}
+
+// An account with enough MainToken to stake `stake_amount` many times.
+fn funded_account<T: Config>(name: &'static str, index: u32) -> T::AccountId {
+    let who = account(name, index, SEED);
+    T::MainToken::make_free_balance_be(&who, stake_amount::<T>().saturating_mul(100u32.into()));
+    who
+}
+
+fn stake_from<T: Config>(who: &T::AccountId) {
+    SimplePool::<T>::stake(RawOrigin::Signed(who.clone()).into(), stake_amount::<T>())
+        .expect("the staker is funded");
+}
+
+// Pay the rewards of `who` to another account, which adds the `Payee` read and a transfer to
+// the settlement. In `CustodyMode::Reserve` the rewards are compounded anyway.
+fn set_payee<T: Config>(who: &T::AccountId, index: u32) {
+    Payee::<T>::insert(
+        who,
+        RewardDestination::Account(account("payee", index, SEED)),
+    );
+}
+
+// Move past the stake cooldown and the next reward distribution.
+fn advance<T: Config>() {
+    let unlock = frame_system::Pallet::<T>::block_number() + BlockToUnlock::<T>::get().into();
+    let reward = LastRewardBlock::<T>::get() + RewardPeriod::<T>::get();
+    let n = unlock.max(reward);
+    frame_system::Pallet::<T>::set_block_number(n);
+    SimplePool::<T>::on_finalize(n);
+}
+
+// Split the ledger of `who` into `MaxDepositChunks` chunks unlocked at the current block, the
+// newest deposited `lag` blocks before the cooldown allows. Every chunk but the newest holds a
+// single unit, so withdrawing from the oldest chunks walks through all of them, and a full
+// ledger makes `deposit` merge the unlocked chunks.
+fn fill_deposit_chunks<T: Config>(who: &T::AccountId, lag: u32) {
+    let count = T::MaxDepositChunks::get();
+    let span = BlockToUnlock::<T>::get()
+        .saturating_add(lag)
+        .saturating_add(count);
+    let now = frame_system::Pallet::<T>::block_number().max(span.into());
+    frame_system::Pallet::<T>::set_block_number(now);
+
+    let mut ledger = Ledger::<T>::get(who).expect("the account staked");
+    let oldest = now - span.into();
+    let mut minted = ledger.minted;
+    let mut chunks: Vec<_> = (0..count.saturating_sub(1))
+        .map(|i| {
+            minted = minted.saturating_sub(One::one());
+            DepositChunk {
+                value: One::one(),
+                since: oldest + i.into(),
+            }
+        })
+        .collect();
+    chunks.push(DepositChunk {
+        value: minted,
+        since: oldest + count.saturating_sub(1).into(),
+    });
+    ledger.chunks = BoundedVec::try_from(chunks).expect("count is bounded");
+    Ledger::<T>::insert(who, ledger);
+}
+
+// Queue `count` unbonding requests of `who`, unstaking a small part of its position each time.
+fn fill_unbonding<T: Config>(who: &T::AccountId, count: u32) {
+    let amount =
+        T::StakedToken::free_balance(who) / T::MaxUnbondingChunks::get().saturating_mul(10).into();
+    for _ in 0..count {
+        SimplePool::<T>::unstake(RawOrigin::Signed(who.clone()).into(), amount)
+            .expect("the position is unlocked");
+    }
+}
+
+// Queue `count` changes of the percentage to its current value, enacted at `enact_at`.
+fn fill_pending_changes<T: Config>(count: u32, enact_at: T::BlockNumber) {
+    let percentage = Percentage::<T>::get();
+    let pending: Vec<_> = (0..count)
+        .map(|id| PendingChange {
+            id,
+            change: ParameterChange::Percentage(percentage),
+            enact_at,
+        })
+        .collect();
+    PendingChanges::<T>::put(BoundedVec::try_from(pending).expect("count is bounded"));
+    NextChangeId::<T>::put(count);
+}
+
+benchmarks! {
+    // The caller already staked, so its pending rewards are settled and paid to its payee.
+    stake {
+        setup_pool::<T>();
+        let caller: T::AccountId = whitelisted_caller();
+        T::MainToken::make_free_balance_be(&caller, stake_amount::<T>().saturating_mul(100u32.into()));
+        stake_from::<T>(&caller);
+        set_payee::<T>(&caller, 0);
+        advance::<T>();
+        // The new deposit finds the ledger full of unlocked chunks and merges them.
+        fill_deposit_chunks::<T>(&caller, 0);
+        // The distributed rewards added their backing to the pool.
+        let pooled = TotalPooled::<T>::get();
+        let amount = stake_amount::<T>();
+    }: _(RawOrigin::Signed(caller.clone()), amount)
+    verify {
+        assert_eq!(TotalPooled::<T>::get(), pooled.saturating_add(amount));
+        assert_eq!(Ledger::<T>::get(&caller).expect("staked").chunks.len(), 2);
+    }
+
+    // A partial unstake keeps the ledger and settles the pending rewards to the payee. It walks
+    // through a full ledger and fills the unbonding queue.
+    unstake {
+        setup_pool::<T>();
+        let caller: T::AccountId = whitelisted_caller();
+        T::MainToken::make_free_balance_be(&caller, stake_amount::<T>().saturating_mul(100u32.into()));
+        stake_from::<T>(&caller);
+        set_payee::<T>(&caller, 0);
+        advance::<T>();
+        let queued = T::MaxUnbondingChunks::get().saturating_sub(1);
+        fill_unbonding::<T>(&caller, queued);
+        fill_deposit_chunks::<T>(&caller, 0);
+        let amount = T::StakedToken::free_balance(&caller) / 10u32.into();
+    }: _(RawOrigin::Signed(caller.clone()), amount)
+    verify {
+        assert_eq!(Unbonding::<T>::get(&caller).len() as u32, queued.saturating_add(1));
+        assert_eq!(Ledger::<T>::get(&caller).expect("staked").chunks.len(), 1);
+    }
+
+    withdraw_unbonded {
+        setup_pool::<T>();
+        let caller: T::AccountId = whitelisted_caller();
+        T::MainToken::make_free_balance_be(&caller, stake_amount::<T>().saturating_mul(100u32.into()));
+        stake_from::<T>(&caller);
+        advance::<T>();
+        // Every request of a full queue is released.
+        fill_unbonding::<T>(&caller, T::MaxUnbondingChunks::get());
+        advance::<T>();
+    }: _(RawOrigin::Signed(caller.clone()))
+    verify {
+        assert!(Unbonding::<T>::get(&caller).is_empty());
+    }
+
+    // Both accounts have a full ledger with pending rewards, paid to their own payee. The
+    // chunks of the receiver are older, so the transferred one makes it merge them.
+    transfer {
+        setup_pool::<T>();
+        let caller: T::AccountId = whitelisted_caller();
+        T::MainToken::make_free_balance_be(&caller, stake_amount::<T>().saturating_mul(100u32.into()));
+        let recv = funded_account::<T>("recv", 0);
+        stake_from::<T>(&caller);
+        stake_from::<T>(&recv);
+        set_payee::<T>(&caller, 0);
+        set_payee::<T>(&recv, 1);
+        advance::<T>();
+        fill_deposit_chunks::<T>(&recv, 1);
+        fill_deposit_chunks::<T>(&caller, 0);
+        let amount = T::StakedToken::free_balance(&caller) / 2u32.into();
+        let recv_balance = T::StakedToken::free_balance(&recv);
+    }: _(RawOrigin::Signed(caller.clone()), recv.clone(), amount)
+    verify {
+        assert_eq!(T::StakedToken::free_balance(&recv), recv_balance.saturating_add(amount));
+    }
+
+    claim_rewards {
+        setup_pool::<T>();
+        let caller: T::AccountId = whitelisted_caller();
+        T::MainToken::make_free_balance_be(&caller, stake_amount::<T>().saturating_mul(100u32.into()));
+        stake_from::<T>(&caller);
+        advance::<T>();
+        assert!(!SimplePool::<T>::pending_rewards(&caller).is_zero());
+    }: _(RawOrigin::Signed(caller.clone()))
+    verify {
+        assert!(SimplePool::<T>::pending_rewards(&caller).is_zero());
+    }
+
+    // The rewards accrued so far are paid to the old destination.
+    set_reward_destination {
+        setup_pool::<T>();
+        let caller: T::AccountId = whitelisted_caller();
+        T::MainToken::make_free_balance_be(&caller, stake_amount::<T>().saturating_mul(100u32.into()));
+        stake_from::<T>(&caller);
+        advance::<T>();
+        let to: T::AccountId = account("to", 0, SEED);
+        let destination = RewardDestination::Account(to);
+    }: _(RawOrigin::Signed(caller.clone()), destination.clone())
+    verify {
+        assert_eq!(Payee::<T>::get(&caller), destination);
+        assert!(SimplePool::<T>::pending_rewards(&caller).is_zero());
+    }
+
+    fund_pot {
+        let caller: T::AccountId = whitelisted_caller();
+        let amount = stake_amount::<T>();
+        T::MainToken::make_free_balance_be(&caller, amount.saturating_mul(2u32.into()));
+    }: _(RawOrigin::Signed(caller.clone()), amount)
+    verify {
+        assert_last_event::<T>(Event::<T>::PotFunded(caller, amount).into());
+    }
+
+    sweep_pot {
+        setup_pool::<T>();
+        let origin = T::AdminOrigin::successful_origin();
+        let to: T::AccountId = account("to", 0, SEED);
+        let amount = stake_amount::<T>();
+    }: _<T::Origin>(origin, to.clone(), amount)
+    verify {
+        assert_last_event::<T>(Event::<T>::PotSwept(to, amount).into());
+    }
+
+    // The step of the percentage is measured from the last pending change.
+    change_percentage {
+        let origin = T::AdminOrigin::successful_origin();
+        let now = frame_system::Pallet::<T>::block_number();
+        fill_pending_changes::<T>(T::MaxPendingChanges::get().saturating_sub(1), now + T::ParameterChangeDelay::get());
+        let percentage = Percentage::<T>::get();
+    }: _<T::Origin>(origin, percentage)
+    verify {
+        assert_eq!(NextChangeId::<T>::get(), T::MaxPendingChanges::get());
+    }
+
+    change_block_time {
+        let origin = T::AdminOrigin::successful_origin();
+        let block_time = T::MaxBlockToUnlock::get();
+    }: _<T::Origin>(origin, block_time)
+    verify {
+        assert_eq!(NextChangeId::<T>::get(), 1);
+    }
+
+    change_reward_period {
+        let origin = T::AdminOrigin::successful_origin();
+        let reward_period = T::MaxRewardPeriod::get();
+    }: _<T::Origin>(origin, reward_period)
+    verify {
+        assert_eq!(NextChangeId::<T>::get(), 1);
+    }
+
+    change_stake_limits {
+        let origin = T::AdminOrigin::successful_origin();
+        let min_stake = MinStake::<T>::get();
+        let max_stake = BalanceOf::<T>::max_value();
+    }: _<T::Origin>(origin, min_stake, max_stake, max_stake)
+    verify {
+        assert_eq!(NextChangeId::<T>::get(), 1);
+    }
+
+    // The cancelled change is the last one of a full queue.
+    cancel_parameter_change {
+        let origin = T::AdminOrigin::successful_origin();
+        let count = T::MaxPendingChanges::get();
+        let now = frame_system::Pallet::<T>::block_number();
+        fill_pending_changes::<T>(count, now + One::one());
+        let id = count.saturating_sub(1);
+    }: _<T::Origin>(origin, id)
+    verify {
+        assert_last_event::<T>(Event::<T>::ParameterChangeCancelled(id).into());
+    }
+
+    pause {
+        let origin = T::EmergencyOrigin::successful_origin();
+    }: _<T::Origin>(origin, PauseState::ALL)
+    verify {
+        assert!(SimplePool::<T>::is_paused(PauseState::ALL));
+    }
+
+    unpause {
+        let origin = T::EmergencyOrigin::successful_origin();
+        Paused::<T>::put(PauseState::ALL);
+    }: _<T::Origin>(origin, PauseState::ALL)
+    verify {
+        assert!(!SimplePool::<T>::is_paused(PauseState::STAKE));
+    }
+
+    // The distribution updates a single accumulator, `s` shows it doesn't depend on the stakers.
+    on_finalize_rewards {
+        let s in 1 .. 100;
+        setup_pool::<T>();
+        for i in 0 .. s {
+            let staker = funded_account::<T>("staker", i);
+            stake_from::<T>(&staker);
+        }
+        let n = LastRewardBlock::<T>::get() + RewardPeriod::<T>::get();
+        frame_system::Pallet::<T>::set_block_number(n);
+        let reward_per_share = RewardPerShare::<T>::get();
+    }: {
+        SimplePool::<T>::on_finalize(n);
+    }
+    verify {
+        assert_eq!(LastRewardBlock::<T>::get(), n);
+        assert!(RewardPerShare::<T>::get() > reward_per_share);
+    }
+
+    on_initialize_enact {
+        let c in 0 .. T::MaxPendingChanges::get();
+        let n = frame_system::Pallet::<T>::block_number();
+        fill_pending_changes::<T>(c, n);
+    }: {
+        SimplePool::<T>::on_initialize(n);
+    }
+    verify {
+        assert!(PendingChanges::<T>::get().is_empty());
+    }
+
+    // A failed invariant pauses staking.
+    check_invariants {
+        OwedRewards::<T>::put(stake_amount::<T>());
+    }: {
+        SimplePool::<T>::enforce_invariants();
+    }
+    verify {
+        assert!(SimplePool::<T>::is_paused(PauseState::STAKE));
+    }
+
+    impl_benchmark_test_suite!(SimplePool, crate::mock::new_test_ext(), crate::mock::Test);
+}
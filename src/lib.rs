@@ -2,6 +2,19 @@
 
 pub use pallet::*;
 
+use codec::{Decode, Encode, MaxEncodedLen};
+use frame_support::pallet_prelude::BoundedVec;
+use frame_support::sp_runtime::traits::Zero;
+use frame_support::sp_runtime::Perbill;
+use frame_support::{
+    CloneNoBound, DefaultNoBound, EqNoBound, PartialEqNoBound, RuntimeDebug, RuntimeDebugNoBound,
+};
+use scale_info::TypeInfo;
+pub use simple_pool_primitives::UnbondingChunk;
+use simple_pool_primitives::{mul_div, mul_div_up};
+
+type BalanceOf<T> = <T as Config>::Balance;
+
 #[cfg(test)]
 mod mock;
 
@@ -11,47 +24,508 @@ mod tests;
 #[cfg(feature = "runtime-benchmarks")]
 mod benchmarking;
 
+pub mod migrations;
+pub mod weights;
+
+/// Where the MainToken backing the pool is held.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub enum CustodyMode {
+    /// Reserved on each staker: only the principal in its own ledger can be released.
+    Reserve,
+    /// Transferred to `Pallet::pool_account`: any StakedToken holder can redeem against it.
+    PoolAccount,
+}
+
+/// An invariant of the pool checked on-chain, reported by `Event::InvariantViolated`.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub enum Invariant {
+    /// The pot holds less StakedToken than the rewards owed to the stakers.
+    PotBelowOwedRewards,
+    /// In `CustodyMode::PoolAccount`, the pool account holds less MainToken than `TotalPooled`.
+    CustodyBelowPooled,
+    /// The StakedToken issuance is below the shares minted by the pool and not burned.
+    IssuanceBelowShares,
+    /// The StakedToken issuance is above `TotalMinted` minus `TotalBurned`: StakedToken was
+    /// minted outside of the pool, unbacked.
+    IssuanceAboveMinted,
+}
+
+/// A set of operations of the pool that can be paused, as a bitflag.
+#[derive(
+    Encode, Decode, Clone, Copy, Default, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen,
+)]
+pub struct PauseState(pub u8);
+
+impl PauseState {
+    /// `stake`.
+    pub const STAKE: Self = Self(1 << 0);
+    /// `unstake`.
+    pub const UNSTAKE: Self = Self(1 << 1);
+    /// `transfer`.
+    pub const TRANSFER: Self = Self(1 << 2);
+    /// The reward distribution in `on_finalize` and the payment of the rewards.
+    pub const REWARDS: Self = Self(1 << 3);
+    /// Every operation.
+    pub const ALL: Self = Self(0b1111);
+
+    /// Whether all the operations in `ops` are in the set.
+    pub fn contains(&self, ops: Self) -> bool {
+        self.0 & ops.0 == ops.0
+    }
+
+    /// Add the operations in `ops` to the set.
+    pub fn insert(&mut self, ops: Self) {
+        self.0 |= ops.0;
+    }
+
+    /// Remove the operations in `ops` from the set.
+    pub fn remove(&mut self, ops: Self) {
+        self.0 &= !ops.0;
+    }
+
+    /// Whether any of the operations in `ops` is in the set.
+    pub fn intersects(&self, ops: Self) -> bool {
+        self.0 & ops.0 != 0
+    }
+
+    /// Whether the set holds at least one operation and only known ones.
+    pub fn is_valid(&self) -> bool {
+        self.0 != 0 && self.0 & !Self::ALL.0 == 0
+    }
+}
+
+/// A change of a parameter of the pool, applied once its timelock expires.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub enum ParameterChange<Balance, BlockNumber> {
+    /// Set `Percentage`.
+    Percentage(Perbill),
+    /// Set `BlockToUnlock`.
+    BlockToUnlock(u32),
+    /// Set `RewardPeriod`.
+    RewardPeriod(BlockNumber),
+    /// Set `MinStake`, `MaxStakePerAccount` and `MaxTotalStaked`.
+    StakeLimits {
+        min_stake: Balance,
+        max_stake_per_account: Balance,
+        max_total_staked: Balance,
+    },
+}
+
+/// A `ParameterChange` waiting for its timelock.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct PendingChange<Balance, BlockNumber> {
+    /// The identifier used to cancel the change.
+    pub id: u32,
+    /// The change to apply.
+    pub change: ParameterChange<Balance, BlockNumber>,
+    /// The block in which the change is applied.
+    pub enact_at: BlockNumber,
+}
+
+/// Where the StakedToken rewards of an account are paid.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub enum RewardDestination<AccountId> {
+    /// Paid to the account and tracked by its ledger as a new deposit, so they earn rewards too.
+    Compound,
+    /// Paid to the free balance of the account, without a cooldown and without earning rewards.
+    Free,
+    /// Paid to the free balance of another account.
+    Account(AccountId),
+}
+
+impl<AccountId> Default for RewardDestination<AccountId> {
+    fn default() -> Self {
+        RewardDestination::Compound
+    }
+}
+
+/// The totals of the pool, as returned by `Pallet::pool_totals`.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct PoolTotals<Balance> {
+    /// The MainToken backing the StakedToken.
+    pub total_pooled: Balance,
+    /// The StakedToken in circulation, the rewards owed by the pot included.
+    pub staked_supply: Balance,
+    /// The StakedToken tracked by the ledgers, which earns rewards.
+    pub total_shares: Balance,
+    /// The MainToken in the reward pot, not distributed yet.
+    pub pot: Balance,
+    /// The rewards distributed and not paid yet, which are kept in the pot.
+    pub owed_rewards: Balance,
+}
+
+/// The staking position of an account, as returned by `Pallet::position`.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct Position<Balance> {
+    /// The StakedToken owned by the account.
+    pub staked: Balance,
+    /// The MainToken `staked` can be redeemed for at the current exchange rate.
+    pub value: Balance,
+    /// The MainToken staked by the account and not yet unstaked.
+    pub principal: Balance,
+    /// The StakedToken still in its cooldown.
+    pub locked: Balance,
+    /// The StakedToken rewards the account can claim.
+    pub pending_rewards: Balance,
+}
+
+/// A deposit of StakedToken tracked by the `Ledger`.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct DepositChunk<Balance, BlockNumber> {
+    /// The amount of StakedToken deposited.
+    pub value: Balance,
+    /// The block in which the deposit was made, its cooldown starts from here.
+    pub since: BlockNumber,
+}
+
+/// The staking position of an account.
+#[derive(
+    Encode,
+    Decode,
+    CloneNoBound,
+    PartialEqNoBound,
+    EqNoBound,
+    DefaultNoBound,
+    RuntimeDebugNoBound,
+    TypeInfo,
+    MaxEncodedLen,
+)]
+#[codec(mel_bound(T: Config))]
+#[scale_info(skip_type_params(T))]
+pub struct StakingLedger<T: Config> {
+    /// The MainToken staked by the account and not yet unstaked.
+    pub principal: BalanceOf<T>,
+    /// The StakedToken tracked by the ledger, always the sum of `chunks`.
+    pub minted: BalanceOf<T>,
+    /// The shares earning rewards: `minted`, capped at the StakedToken held by the account
+    /// when the ledger was last stored.
+    pub shares: BalanceOf<T>,
+    /// The deposits of the account, oldest first.
+    pub chunks: BoundedVec<DepositChunk<BalanceOf<T>, T::BlockNumber>, T::MaxDepositChunks>,
+    /// The StakedToken rewards settled but not paid yet, because the pot could not cover them.
+    pub unclaimed: BalanceOf<T>,
+    /// The block of the last `stake` of the account. Received and compounded deposits don't count.
+    pub last_stake: Option<T::BlockNumber>,
+}
+
+impl<T: Config> StakingLedger<T> {
+    /// Whether `chunk` completed its cooldown at block `now`.
+    fn is_unlocked(
+        chunk: &DepositChunk<BalanceOf<T>, T::BlockNumber>,
+        now: T::BlockNumber,
+    ) -> bool {
+        now >= chunk.since + BlockToUnlock::<T>::get().into()
+    }
+
+    /// The StakedToken of the chunks that are still in their cooldown at block `now`.
+    pub fn locked(&self, now: T::BlockNumber) -> BalanceOf<T> {
+        self.chunks
+            .iter()
+            .filter(|chunk| !Self::is_unlocked(chunk, now))
+            .fold(Zero::zero(), |acc: BalanceOf<T>, chunk| {
+                acc.saturating_add(chunk.value)
+            })
+    }
+
+    /// The StakedToken of the chunks that completed their cooldown at block `now`.
+    pub fn unlocked(&self, now: T::BlockNumber) -> BalanceOf<T> {
+        self.minted.saturating_sub(self.locked(now))
+    }
+
+    /// Add a deposit of `value` StakedToken backed by `principal` MainToken made at block `since`,
+    /// keeping the chunks ordered by `since`.
+    ///
+    /// Once `MaxDepositChunks` is reached the chunks unlocked at block `now` are merged together,
+    /// then the deposit is merged into the oldest chunk made after it, which keeps its cooldown.
+    /// Fails if the deposit is newer than every chunk, so it never restarts the cooldown of the
+    /// chunks already in the ledger.
+    pub fn deposit(
+        &mut self,
+        value: BalanceOf<T>,
+        principal: BalanceOf<T>,
+        since: T::BlockNumber,
+        now: T::BlockNumber,
+    ) -> Result<(), ()> {
+        let index = self.chunks.partition_point(|chunk| chunk.since < since);
+        match self.chunks.get_mut(index) {
+            Some(chunk) if chunk.since == since => chunk.value = chunk.value.saturating_add(value),
+            _ => {
+                if self.chunks.len() as u32 >= T::MaxDepositChunks::get() {
+                    self.merge_unlocked(now);
+                }
+                let index = self.chunks.partition_point(|chunk| chunk.since < since);
+                if self
+                    .chunks
+                    .try_insert(index, DepositChunk { value, since })
+                    .is_err()
+                {
+                    let chunk = self.chunks.get_mut(index).ok_or(())?;
+                    chunk.value = chunk.value.saturating_add(value);
+                }
+            }
+        }
+
+        self.minted = self.minted.saturating_add(value);
+        self.principal = self.principal.saturating_add(principal);
+        Ok(())
+    }
+
+    /// Merge a deposit of `value` StakedToken backed by `principal` MainToken made at block
+    /// `since` into the oldest chunk made at or after it, which keeps its cooldown, without
+    /// adding a chunk. Fails if every chunk is older than the deposit.
+    pub fn join_later(
+        &mut self,
+        value: BalanceOf<T>,
+        principal: BalanceOf<T>,
+        since: T::BlockNumber,
+    ) -> Result<(), ()> {
+        let index = self.chunks.partition_point(|chunk| chunk.since < since);
+        let chunk = self.chunks.get_mut(index).ok_or(())?;
+        chunk.value = chunk.value.saturating_add(value);
+
+        self.minted = self.minted.saturating_add(value);
+        self.principal = self.principal.saturating_add(principal);
+        Ok(())
+    }
+
+    /// Merge a deposit of `value` StakedToken backed by `principal` MainToken into the newest
+    /// chunk, for the deposits of the account itself that `deposit` can't place.
+    /// The cooldown of the chunk restarts from `restart_at`, if given.
+    pub fn merge_into_newest(
+        &mut self,
+        value: BalanceOf<T>,
+        principal: BalanceOf<T>,
+        restart_at: Option<T::BlockNumber>,
+    ) {
+        if let Some(newest) = self.chunks.last_mut() {
+            newest.value = newest.value.saturating_add(value);
+            if let Some(since) = restart_at {
+                newest.since = newest.since.max(since);
+            }
+            self.minted = self.minted.saturating_add(value);
+            self.principal = self.principal.saturating_add(principal);
+        }
+    }
+
+    /// Merge the chunks unlocked at block `now`, i.e. the oldest ones, into the newest of them.
+    /// It is still unlocked, so no cooldown restarts.
+    fn merge_unlocked(&mut self, now: T::BlockNumber) {
+        let unlocked = self
+            .chunks
+            .iter()
+            .take_while(|chunk| Self::is_unlocked(chunk, now))
+            .count();
+        for _ in 1..unlocked {
+            let oldest = self.chunks.remove(0);
+            if let Some(next) = self.chunks.get_mut(0) {
+                next.value = next.value.saturating_add(oldest.value);
+            }
+        }
+    }
+
+    /// Remove up to `value` StakedToken from the chunks unlocked at block `now`, oldest first.
+    /// Returns the principal backing the removed StakedToken.
+    pub fn withdraw_unlocked(&mut self, value: BalanceOf<T>, now: T::BlockNumber) -> BalanceOf<T> {
+        let mut remaining = value;
+        for chunk in self.chunks.iter_mut() {
+            if remaining.is_zero() {
+                break;
+            }
+            if Self::is_unlocked(chunk, now) {
+                let taken = remaining.min(chunk.value);
+                chunk.value = chunk.value.saturating_sub(taken);
+                remaining = remaining.saturating_sub(taken);
+            }
+        }
+        self.chunks.retain(|chunk| !chunk.value.is_zero());
+
+        self.take_principal(value.saturating_sub(remaining))
+    }
+
+    /// Remove up to `value` StakedToken from the newest chunks, regardless of their cooldown.
+    /// Returns the removed StakedToken, the principal backing it and the newest block it was deposited in.
+    pub fn withdraw_newest(
+        &mut self,
+        value: BalanceOf<T>,
+    ) -> (BalanceOf<T>, BalanceOf<T>, Option<T::BlockNumber>) {
+        let mut remaining = value;
+        let mut since = None;
+        while !remaining.is_zero() {
+            let chunk = match self.chunks.last_mut() {
+                Some(chunk) => chunk,
+                None => break,
+            };
+            let taken = remaining.min(chunk.value);
+            chunk.value = chunk.value.saturating_sub(taken);
+            remaining = remaining.saturating_sub(taken);
+            since = since.max(Some(chunk.since));
+            if chunk.value.is_zero() {
+                self.chunks.pop();
+            }
+        }
+
+        let withdrawn = value.saturating_sub(remaining);
+        (withdrawn, self.take_principal(withdrawn), since)
+    }
+
+    /// Account for `value` StakedToken leaving the ledger, returning its share of principal.
+    fn take_principal(&mut self, value: BalanceOf<T>) -> BalanceOf<T> {
+        let principal = if value >= self.minted {
+            self.principal
+        } else {
+            mul_div(self.principal, value, self.minted).unwrap_or_else(Zero::zero)
+        };
+        self.principal = self.principal.saturating_sub(principal);
+        self.minted = self.minted.saturating_sub(value);
+        principal
+    }
+}
+
 #[frame_support::pallet]
 pub mod pallet {
+    use super::{
+        migrations, mul_div, mul_div_up, BalanceOf, CustodyMode, Invariant, ParameterChange,
+        PauseState, PendingChange, PoolTotals, Position, RewardDestination, StakingLedger,
+        UnbondingChunk,
+    };
     use frame_support::pallet_prelude::*;
     use frame_support::sp_runtime::DispatchResult;
-    use frame_support::traits::tokens::{ExistenceRequirement, WithdrawReasons};
-    use frame_support::traits::{Currency, Get, ReservableCurrency};
-
-    use frame_support::sp_runtime::traits::AccountIdConversion;
+    use frame_support::storage::with_storage_layer;
+    use frame_support::traits::tokens::{BalanceStatus, ExistenceRequirement, WithdrawReasons};
+    use frame_support::traits::{
+        Currency, Get, Imbalance, NamedReservableCurrency, OnRuntimeUpgrade,
+    };
+
+    use crate::weights::WeightInfo;
+    use frame_support::sp_runtime::traits::{
+        AccountIdConversion, AtLeast32BitUnsigned, One, Saturating, Zero,
+    };
+    use frame_support::sp_runtime::Perbill;
+    use frame_support::sp_runtime::{
+        ArithmeticError, FixedPointNumber, FixedPointOperand, FixedU128,
+    };
+    use frame_support::sp_std::collections::btree_set::BTreeSet;
+    use frame_support::sp_std::vec::Vec;
     use frame_support::PalletId;
     use frame_system::pallet_prelude::*;
 
-    // Allows easy access our Pallet's `Balance` type. Comes from `Currency` interface.
-    // The balance of the `StakedTokenBalance` type.
-    type StakedTokenBalance<T> =
-        <<T as Config>::StakedToken as Currency<<T as frame_system::Config>::AccountId>>::Balance;
-
-    type Balance = u128;
-
     #[pallet::config]
     pub trait Config: frame_system::Config + pallet_democracy::Config {
         /// Because this pallet emits events, it depends on the runtime's definition of an event.
         type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
 
-        /// The "native" Token to stake
-        type MainToken: ReservableCurrency<Self::AccountId, Balance = Balance>;
+        /// The balance type of both the MainToken and the StakedToken.
+        type Balance: Parameter
+            + Member
+            + AtLeast32BitUnsigned
+            + FixedPointOperand
+            + MaxEncodedLen
+            + Default
+            + Copy
+            + MaybeSerializeDeserialize;
+
+        /// The "native" Token to stake.
+        /// In `CustodyMode::Reserve` it is reserved under the identifier given by `PalletId`.
+        type MainToken: NamedReservableCurrency<
+            Self::AccountId,
+            Balance = Self::Balance,
+            ReserveIdentifier = [u8; 8],
+        >;
 
         /// The "liquid" Token given after staking
-        type StakedToken: Currency<Self::AccountId, Balance = Balance>;
+        type StakedToken: Currency<Self::AccountId, Balance = Self::Balance>;
 
         #[pallet::constant]
         type PalletId: Get<PalletId>;
+
+        /// The maximum number of pending unbonding requests per account.
+        #[pallet::constant]
+        type MaxUnbondingChunks: Get<u32>;
+
+        /// The maximum number of deposit chunks tracked per account.
+        #[pallet::constant]
+        type MaxDepositChunks: Get<u32>;
+
+        /// Where the staked MainToken is held. Switching from `Reserve` to `PoolAccount`
+        /// requires running `migrations::MigrateToPoolAccount` in the same runtime upgrade.
+        /// In `PoolAccount` mode the pool account must be endowed with the existential deposit.
+        #[pallet::constant]
+        type Custody: Get<CustodyMode>;
+
+        /// The origin allowed to change the parameters of the pool and to sweep the pot,
+        /// e.g. root, a council or a referendum.
+        type AdminOrigin: EnsureOrigin<Self::Origin>;
+
+        /// The origin allowed to pause and unpause the operations of the pool.
+        type EmergencyOrigin: EnsureOrigin<Self::Origin>;
+
+        /// The number of blocks between two reward distributions, until the governance changes it.
+        #[pallet::constant]
+        type DefaultRewardPeriod: Get<Self::BlockNumber>;
+
+        /// The maximum number of blocks between two reward distributions.
+        #[pallet::constant]
+        type MaxRewardPeriod: Get<Self::BlockNumber>;
+
+        /// The minimum `BlockToUnlock` the governance can set.
+        #[pallet::constant]
+        type MinBlockToUnlock: Get<u32>;
+
+        /// The maximum `BlockToUnlock` the governance can set.
+        #[pallet::constant]
+        type MaxBlockToUnlock: Get<u32>;
+
+        /// The maximum `Percentage` the governance can set.
+        #[pallet::constant]
+        type MaxRewardRate: Get<Perbill>;
+
+        /// The maximum difference between the current `Percentage` and the one set by an update.
+        #[pallet::constant]
+        type MaxStepChange: Get<Perbill>;
+
+        /// The minimum value of a position, in MainToken, until the governance changes it.
+        #[pallet::constant]
+        type DefaultMinStake: Get<Self::Balance>;
+
+        /// The maximum value of a position, in MainToken, until the governance changes it.
+        #[pallet::constant]
+        type DefaultMaxStakePerAccount: Get<Self::Balance>;
+
+        /// The maximum MainToken in the pool, until the governance changes it.
+        #[pallet::constant]
+        type DefaultMaxTotalStaked: Get<Self::Balance>;
+
+        /// The number of blocks a parameter change waits before being applied.
+        /// With 0 the changes are applied immediately.
+        #[pallet::constant]
+        type ParameterChangeDelay: Get<Self::BlockNumber>;
+
+        /// The maximum number of parameter changes waiting to be applied.
+        #[pallet::constant]
+        type MaxPendingChanges: Get<u32>;
+
+        /// Whether `on_finalize` checks the cheap invariants of the pool in every block,
+        /// pausing staking when one of them fails.
+        #[pallet::constant]
+        type CheckInvariants: Get<bool>;
+
+        /// Weight information for extrinsics in this pallet.
+        type WeightInfo: WeightInfo;
     }
 
+    /// The current storage version.
+    const STORAGE_VERSION: StorageVersion = StorageVersion::new(1);
+
     #[pallet::pallet]
     #[pallet::generate_store(pub(super) trait Store)]
+    #[pallet::storage_version(STORAGE_VERSION)]
     pub struct Pallet<T>(_);
 
     #[pallet::storage]
-    #[pallet::getter(fn staked_times)]
-    pub(super) type StakedTimes<T: Config> =
-        StorageMap<_, Blake2_128Concat, T::AccountId, T::BlockNumber, OptionQuery>;
+    #[pallet::getter(fn ledger)]
+    /// The staking position of each account: the single source of truth for cooldowns and rewards.
+    pub type Ledger<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, StakingLedger<T>, OptionQuery>;
 
     #[pallet::type_value]
     pub fn DefaultBlockTime<T: Config>() -> u32 {
@@ -59,44 +533,234 @@ pub mod pallet {
     }
 
     #[pallet::type_value]
-    pub fn DefaultPercentage<T: Config>() -> u8 {
-        1_u8
+    pub fn DefaultPercentage<T: Config>() -> Perbill {
+        Perbill::from_percent(1)
+    }
+
+    #[pallet::type_value]
+    pub fn DefaultRewardPeriod<T: Config>() -> T::BlockNumber {
+        T::DefaultRewardPeriod::get()
     }
 
     #[pallet::storage]
     #[pallet::getter(fn percentage)]
-    /// The percentage of "liquid" Token that a user receives after staking.
-    /// X DOT gives (X + Percentage% X) of LDOT
-    pub type Percentage<T: Config> = StorageValue<_, u8, ValueQuery, DefaultPercentage<T>>;
+    /// The share of "liquid" Token that a staker receives as a reward every `RewardPeriod` blocks.
+    pub type Percentage<T: Config> = StorageValue<_, Perbill, ValueQuery, DefaultPercentage<T>>;
 
     #[pallet::storage]
     #[pallet::getter(fn block_to_unlock)]
     /// The number of blocks that a user must wait before they can stake/unstake.
     pub type BlockToUnlock<T: Config> = StorageValue<_, u32, ValueQuery, DefaultBlockTime<T>>;
 
+    #[pallet::storage]
+    #[pallet::getter(fn total_pooled)]
+    /// The total amount of MainToken backing the StakedToken held by the users.
+    /// Together with `Pallet::staked_supply` it defines the exchange rate of the pool.
+    pub type TotalPooled<T: Config> = StorageValue<_, BalanceOf<T>, ValueQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn unbonding)]
+    /// The pending withdrawals of each account, oldest first.
+    pub type Unbonding<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        BoundedVec<UnbondingChunk<BalanceOf<T>, T::BlockNumber>, T::MaxUnbondingChunks>,
+        ValueQuery,
+    >;
+
+    #[pallet::type_value]
+    pub fn DefaultMinStake<T: Config>() -> BalanceOf<T> {
+        T::DefaultMinStake::get()
+    }
+
+    #[pallet::type_value]
+    pub fn DefaultMaxStakePerAccount<T: Config>() -> BalanceOf<T> {
+        T::DefaultMaxStakePerAccount::get()
+    }
+
+    #[pallet::type_value]
+    pub fn DefaultMaxTotalStaked<T: Config>() -> BalanceOf<T> {
+        T::DefaultMaxTotalStaked::get()
+    }
+
+    #[pallet::storage]
+    #[pallet::getter(fn min_stake)]
+    /// The minimum value of a position, in MainToken at the current exchange rate.
+    pub type MinStake<T: Config> = StorageValue<_, BalanceOf<T>, ValueQuery, DefaultMinStake<T>>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn max_stake_per_account)]
+    /// The maximum value of a position, in MainToken at the current exchange rate.
+    pub type MaxStakePerAccount<T: Config> =
+        StorageValue<_, BalanceOf<T>, ValueQuery, DefaultMaxStakePerAccount<T>>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn max_total_staked)]
+    /// The maximum MainToken in the pool.
+    pub type MaxTotalStaked<T: Config> =
+        StorageValue<_, BalanceOf<T>, ValueQuery, DefaultMaxTotalStaked<T>>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn reward_period)]
+    /// The number of blocks between two reward distributions.
+    pub type RewardPeriod<T: Config> =
+        StorageValue<_, T::BlockNumber, ValueQuery, DefaultRewardPeriod<T>>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn last_reward_block)]
+    /// The block of the last reward distribution, the next one happens `RewardPeriod` blocks later.
+    pub type LastRewardBlock<T: Config> = StorageValue<_, T::BlockNumber, ValueQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn pending_changes)]
+    /// The parameter changes waiting for their timelock, in the order they were scheduled.
+    pub type PendingChanges<T: Config> = StorageValue<
+        _,
+        BoundedVec<PendingChange<BalanceOf<T>, T::BlockNumber>, T::MaxPendingChanges>,
+        ValueQuery,
+    >;
+
+    #[pallet::storage]
+    /// The identifier of the next scheduled parameter change.
+    pub type NextChangeId<T: Config> = StorageValue<_, u32, ValueQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn pause_state)]
+    /// The operations of the pool currently paused.
+    pub type Paused<T: Config> = StorageValue<_, PauseState, ValueQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn owed_rewards)]
+    /// The StakedToken rewards distributed but not paid yet, held for the stakers in the pot.
+    pub type OwedRewards<T: Config> = StorageValue<_, BalanceOf<T>, ValueQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn total_rewards_paid)]
+    /// The StakedToken paid from the pot as rewards since the pool started.
+    pub type TotalRewardsPaid<T: Config> = StorageValue<_, BalanceOf<T>, ValueQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn total_minted)]
+    /// The StakedToken minted by the pool since it started, counting the issuance at genesis.
+    pub type TotalMinted<T: Config> = StorageValue<_, BalanceOf<T>, ValueQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn total_burned)]
+    /// The StakedToken burned by `unstake` since the pool started.
+    pub type TotalBurned<T: Config> = StorageValue<_, BalanceOf<T>, ValueQuery>;
+
+    #[pallet::storage]
+    /// Whether `PotDepleted` was emitted since the pot last covered a whole distribution.
+    pub type PotDepletionReported<T: Config> = StorageValue<_, bool, ValueQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn total_shares)]
+    /// The shares of all the ledgers, i.e. the tracked StakedToken still held by the stakers.
+    pub type TotalShares<T: Config> = StorageValue<_, BalanceOf<T>, ValueQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn reward_per_share)]
+    /// The StakedToken reward accrued by a single share since the pool started.
+    pub type RewardPerShare<T: Config> = StorageValue<_, FixedU128, ValueQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn reward_debt)]
+    /// The part of `RewardPerShare * shares` already settled for each account.
+    pub type RewardDebt<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, BalanceOf<T>, ValueQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn payee)]
+    /// Where the rewards of each account are paid, `RewardDestination::Compound` if not set.
+    pub type Payee<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, RewardDestination<T::AccountId>, ValueQuery>;
+
     #[pallet::event]
     #[pallet::generate_deposit(pub(super) fn deposit_event)]
     pub enum Event<T: Config> {
         /// Event emitted when a MainToken is LOCKED by the owner. [from, amount]
-        MainTokenStaked(T::AccountId, Balance),
+        MainTokenStaked(T::AccountId, BalanceOf<T>),
 
         /// Event emitted when a MainToken is UNLOCKED by the owner. [from, amount]
-        MainTokenUnstaked(T::AccountId, Balance),
+        MainTokenUnstaked(T::AccountId, BalanceOf<T>),
+
+        /// Event emitted when an unbonding request is created by `unstake`. [from, amount, unlock_at]
+        UnbondingRequested(T::AccountId, BalanceOf<T>, T::BlockNumber),
 
         /// Event emitted when a StakedToken is DEPOSITED to the owner. [from, amount]
-        StakedTokenDeposited(T::AccountId, Balance),
+        StakedTokenDeposited(T::AccountId, BalanceOf<T>),
 
         /// Event emitted when a StakedToken is TRANSFERRED by the owner. [from, recv, amount]
-        StakedTokenTransferred(T::AccountId, T::AccountId, StakedTokenBalance<T>),
+        StakedTokenTransferred(T::AccountId, T::AccountId, BalanceOf<T>),
 
         /// Event emitted when a StakedToken is REMOVED from the owner. [from, amount]
-        StakedTokenWithdrawn(T::AccountId, Balance),
+        StakedTokenWithdrawn(T::AccountId, BalanceOf<T>),
 
         /// Event emitted when a StakedToken is ISSUED. [amount]
-        StakedTokenIssued(Balance),
+        StakedTokenIssued(BalanceOf<T>),
 
         /// Event emitted when a StakedToken is BURNED. [amount]
-        StakedTokenBurned(Balance),
+        StakedTokenBurned(BalanceOf<T>),
+
+        /// Event emitted when the reward of an account could not be paid, it stays unclaimed. [who, amount, error]
+        RewardSkipped(T::AccountId, BalanceOf<T>, DispatchError),
+
+        /// Event emitted when the reward of an account has been paid. [who, destination, amount]
+        RewardPaid(T::AccountId, RewardDestination<T::AccountId>, BalanceOf<T>),
+
+        /// Event emitted when an account changes where its rewards are paid. [who, destination]
+        RewardDestinationSet(T::AccountId, RewardDestination<T::AccountId>),
+
+        /// Event emitted when the governance changes the reward rate. [old, new]
+        PercentageChanged(Perbill, Perbill),
+
+        /// Event emitted when the governance changes the cooldown. [old, new]
+        BlockToUnlockChanged(u32, u32),
+
+        /// Event emitted when the governance changes the stake limits. [min_stake, max_stake_per_account, max_total_staked]
+        StakeLimitsChanged(BalanceOf<T>, BalanceOf<T>, BalanceOf<T>),
+
+        /// Event emitted when the governance changes the reward period. [old, new]
+        RewardPeriodChanged(T::BlockNumber, T::BlockNumber),
+
+        /// Event emitted when a parameter change has been scheduled. [id, change, enact_at]
+        ParameterChangeScheduled(
+            u32,
+            ParameterChange<BalanceOf<T>, T::BlockNumber>,
+            T::BlockNumber,
+        ),
+
+        /// Event emitted when a scheduled parameter change has been applied. [id, change]
+        ParameterChangeEnacted(u32, ParameterChange<BalanceOf<T>, T::BlockNumber>),
+
+        /// Event emitted when the governance cancels a scheduled parameter change. [id]
+        ParameterChangeCancelled(u32),
+
+        /// Event emitted when a scheduled parameter change is no longer valid once due, and is dropped. [id, error]
+        ParameterChangeDropped(u32, DispatchError),
+
+        /// Event emitted when operations of the pool have been paused. [ops, state]
+        OperationsPaused(PauseState, PauseState),
+
+        /// Event emitted when operations of the pool have been unpaused. [ops, state]
+        OperationsUnpaused(PauseState, PauseState),
+
+        /// Event emitted when the pot has been funded with MainToken. [who, amount]
+        PotFunded(T::AccountId, BalanceOf<T>),
+
+        /// Event emitted when the governance sweeps MainToken from the pot. [to, amount]
+        PotSwept(T::AccountId, BalanceOf<T>),
+
+        /// Event emitted once when the pot can't cover a distribution, rewards are scaled down. [available, required]
+        PotDepleted(BalanceOf<T>, BalanceOf<T>),
+
+        /// Event emitted when an invariant of the pool fails and staking has been paused. [invariant]
+        InvariantViolated(Invariant),
+
+        /// Event emitted when a migration can't move the reserve of an account under
+        /// `Pallet::reserve_id`, so it stays anonymous. [who, amount, error]
+        ReserveNotConverted(T::AccountId, BalanceOf<T>, DispatchError),
     }
 
     // Errors inform users that something went wrong.
@@ -117,143 +781,399 @@ pub mod pallet {
         /// An account is trying to stake again without waiting the required number of blocks.
         TooFastStake,
 
-        /// An account is trying to stake/unstake/stake a 0 amount of tokens.
+        /// An account is trying to stake/unstake a 0 amount of tokens,
+        /// or an amount that is worth 0 tokens at the current exchange rate.
         ZeroAmount,
 
-        /// The governance is trying to set a value that is > 100%.
+        /// The governance is trying to set a percentage above `MaxRewardRate`.
         PercentageTooHigh,
+
+        /// An account has reached `MaxUnbondingChunks` pending unbonding requests.
+        TooManyUnbondingChunks,
+
+        /// An account has no unbonding request ready to be withdrawn.
+        NothingToWithdraw,
+
+        /// The MainToken could not be reserved.
+        ReserveFailed,
+
+        /// The MainToken could not be fully unreserved.
+        UnreserveFailed,
+
+        /// The StakedToken could not be withdrawn.
+        WithdrawFailed,
+
+        /// The StakedToken could not be fully minted.
+        MintFailed,
+
+        /// The StakedToken could not be transferred.
+        TransferFailed,
+
+        /// The pot does not hold enough StakedToken to pay a reward.
+        PotInsufficient,
+
+        /// The MainToken backing the transferred StakedToken could not be moved.
+        RepatriateFailed,
+
+        /// An account has no reward to claim.
+        NothingToClaim,
+
+        /// The governance is trying to set a reward period of 0 or above `MaxRewardPeriod`.
+        RewardPeriodOutOfBounds,
+
+        /// The governance is trying to sweep rewards owed to the stakers.
+        SweepExceedsSurplus,
+
+        /// The governance is trying to set a `BlockToUnlock` out of `MinBlockToUnlock..=MaxBlockToUnlock`.
+        BlockToUnlockOutOfBounds,
+
+        /// The governance is trying to change the percentage by more than `MaxStepChange`.
+        StepChangeTooLarge,
+
+        /// The operation is paused by the emergency origin.
+        Paused,
+
+        /// The emergency origin is trying to pause or unpause no operation, or an unknown one.
+        InvalidPauseState,
+
+        /// The position would be worth less than `MinStake`, but not 0.
+        BelowMinimumStake,
+
+        /// The position would be worth more than `MaxStakePerAccount`.
+        AccountCapExceeded,
+
+        /// The pool would hold more than `MaxTotalStaked`.
+        PoolCapReached,
+
+        /// The governance is trying to set a `MinStake` above `MaxStakePerAccount`.
+        InvalidStakeLimits,
+
+        /// `MaxPendingChanges` parameter changes are already waiting to be applied.
+        TooManyPendingChanges,
+
+        /// There is no pending parameter change with the given identifier.
+        UnknownParameterChange,
+
+        /// The rewards can't be paid to the pot itself, nor outside of the ledger in `CustodyMode::Reserve`.
+        InvalidRewardDestination,
+
+        /// The ledger of the receiver is full of chunks in their cooldown, all older than the transferred ones.
+        TooManyDepositChunks,
+
+        /// A transfer worth less than `MinStake` can only join a chunk of the receiver made at or after it.
+        TransferBelowMinimumStake,
+
+        /// In `CustodyMode::Reserve` only the StakedToken tracked by the ledger of the caller can be unstaked.
+        UntrackedStakedToken,
     }
 
     #[pallet::genesis_config]
-    pub struct GenesisConfig;
+    pub struct GenesisConfig<T: Config> {
+        /// The MainToken issued into the pot to pay the rewards.
+        pub pot: BalanceOf<T>,
+        /// The initial `Percentage`, at most 100%.
+        pub percentage: Perbill,
+        /// The initial `BlockToUnlock`.
+        pub block_to_unlock: u32,
+        /// The initial `RewardPeriod`, between 1 and `MaxRewardPeriod`.
+        pub reward_period: T::BlockNumber,
+        /// The MainToken staked by each account at genesis. It must be free at genesis.
+        pub stakers: Vec<(T::AccountId, BalanceOf<T>)>,
+    }
 
     #[cfg(feature = "std")]
-    impl Default for GenesisConfig {
+    impl<T: Config> Default for GenesisConfig<T> {
         fn default() -> Self {
-            Self
+            Self {
+                pot: Zero::zero(),
+                percentage: DefaultPercentage::<T>::get(),
+                block_to_unlock: DefaultBlockTime::<T>::get(),
+                reward_period: T::DefaultRewardPeriod::get(),
+                stakers: Vec::new(),
+            }
         }
     }
 
     #[pallet::genesis_build]
-    impl<T: Config> GenesisBuild<T> for GenesisConfig {
+    impl<T: Config> GenesisBuild<T> for GenesisConfig<T> {
         fn build(&self) {
+            assert!(
+                self.percentage <= T::MaxRewardRate::get(),
+                "`percentage` must be at most `MaxRewardRate`"
+            );
+            assert!(
+                (T::MinBlockToUnlock::get()..=T::MaxBlockToUnlock::get())
+                    .contains(&self.block_to_unlock),
+                "`block_to_unlock` must be between `MinBlockToUnlock` and `MaxBlockToUnlock`"
+            );
+            assert!(
+                !self.reward_period.is_zero() && self.reward_period <= T::MaxRewardPeriod::get(),
+                "`reward_period` must be between 1 and `MaxRewardPeriod`"
+            );
+            Percentage::<T>::put(self.percentage);
+            BlockToUnlock::<T>::put(self.block_to_unlock);
+            RewardPeriod::<T>::put(self.reward_period);
+
             // Create POT account
-            let pallet_account_id: T::AccountId = T::PalletId::get().into_account_truncating();
-            let amount = 1_000_000_000;
-            let _ = T::StakedToken::issue(amount);
-            if T::StakedToken::free_balance(&pallet_account_id) < amount {
-                let _ = T::StakedToken::make_free_balance_be(&pallet_account_id, amount);
+            if !self.pot.is_zero() {
+                let issued = T::MainToken::deposit_creating(&Self::account_id(), self.pot);
+                assert_eq!(
+                    issued.peek(),
+                    self.pot,
+                    "`pot` must be above the existential deposit"
+                );
+            }
+
+            // The initial positions are staked as if `stake` was called at genesis.
+            let mut stakers = BTreeSet::new();
+            for (who, amount) in &self.stakers {
+                assert!(stakers.insert(who), "duplicate staker in `stakers`");
+                assert!(!amount.is_zero(), "staked amount must be positive");
+                assert!(
+                    T::MainToken::free_balance(who) >= *amount,
+                    "staker doesn't have enough MainToken"
+                );
+
+                let minted = Pallet::<T>::main_to_staked(*amount)
+                    .filter(|minted| !minted.is_zero())
+                    .expect("staked amount must be worth at least one StakedToken");
+                Pallet::<T>::lock_main(who, *amount).expect("MainToken can't be locked");
+                TotalPooled::<T>::mutate(|pooled| *pooled = pooled.saturating_add(*amount));
+
+                let issued = T::StakedToken::deposit_creating(who, minted);
+                assert_eq!(issued.peek(), minted, "StakedToken can't be minted");
+                Pallet::<T>::mutate_ledger(who, |ledger| {
+                    ledger
+                        .deposit(minted, *amount, Zero::zero(), Zero::zero())
+                        .map_err(|_| Error::<T>::TooManyDepositChunks)?;
+                    ledger.last_stake = Some(Zero::zero());
+                    Ok(())
+                })
+                .expect("ledger can't be stored");
             }
+
+            // The StakedToken endowed by the genesis of `StakedToken` counts as minted.
+            TotalMinted::<T>::put(T::StakedToken::total_issuance());
         }
     }
 
     #[pallet::call]
     impl<T: Config> Pallet<T> {
-        #[pallet::weight(T::DbWeight::get().writes(1))]
-        pub fn stake(origin: OriginFor<T>, amount: Balance) -> DispatchResult {
+        #[pallet::weight(T::WeightInfo::stake())]
+        pub fn stake(origin: OriginFor<T>, amount: BalanceOf<T>) -> DispatchResult {
             let who = ensure_signed(origin)?;
 
-            ensure!(amount > 0_u8.into(), Error::<T>::ZeroAmount);
+            Self::ensure_not_paused(PauseState::STAKE)?;
+
+            ensure!(!amount.is_zero(), Error::<T>::ZeroAmount);
 
             ensure!(
                 T::MainToken::free_balance(&who) >= amount,
                 Error::<T>::NotEnoughMainToken
             );
 
-            let last_stake_time = <StakedTimes<T>>::get(&who).unwrap_or_else(|| 0_u8.into());
             let now = <frame_system::Pallet<T>>::block_number();
 
+            if let Some(last_stake) = <Ledger<T>>::get(&who).and_then(|l| l.last_stake) {
+                ensure!(
+                    now >= last_stake + BlockToUnlock::<T>::get().into(),
+                    Error::<T>::TooFastStake
+                );
+            }
+
+            // Pay the pending rewards first, so they are not priced into the new `StakedToken`.
+            if <Ledger<T>>::contains_key(&who) {
+                Self::mutate_ledger(&who, |_| Ok(()))?;
+            }
+
+            // Compute the `StakedToken` share before changing the pool.
+            let staked_token_issued =
+                Self::main_to_staked(amount).ok_or(ArithmeticError::Overflow)?;
+            ensure!(!staked_token_issued.is_zero(), Error::<T>::ZeroAmount);
+            let total_pooled = TotalPooled::<T>::get()
+                .checked_add(&amount)
+                .ok_or(ArithmeticError::Overflow)?;
             ensure!(
-                now >= last_stake_time + BlockToUnlock::<T>::get().into(),
-                Error::<T>::TooFastStake
+                total_pooled <= MaxTotalStaked::<T>::get(),
+                Error::<T>::PoolCapReached
             );
 
-            // Reserve the `MainToken` token.
-            let _ = T::MainToken::reserve(&who, amount);
-            Self::deposit_event(Event::MainTokenStaked(who.clone(), amount));
-
-            let percentage = Percentage::<T>::get().into();
-            let bonus_token = amount
-                .checked_div(100)
-                .unwrap_or(0)
-                .checked_mul(percentage)
-                .unwrap_or(amount);
+            // The limits apply to the whole position of the user, at the current exchange rate.
+            let position = Self::position_value(&who)?.saturating_add(amount);
+            ensure!(
+                position >= MinStake::<T>::get(),
+                Error::<T>::BelowMinimumStake
+            );
+            ensure!(
+                position <= MaxStakePerAccount::<T>::get(),
+                Error::<T>::AccountCapExceeded
+            );
 
-            let staked_token_issued = amount.checked_add(bonus_token).unwrap_or(amount);
+            // Move the `MainToken` token into the custody of the pool.
+            Self::lock_main(&who, amount)?;
+            TotalPooled::<T>::put(total_pooled);
+            Self::deposit_event(Event::MainTokenStaked(who.clone(), amount));
 
-            // Issue new `StakedToken` tokens.
-            // This is infallible, but doesn’t guarantee that the entire amount is issued, for example in the case of overflow.
-            let issued = T::StakedToken::issue(staked_token_issued);
+            // Issue new `StakedToken` tokens and deposit them to the user.
+            // This doesn’t guarantee that the entire amount is issued, so the imbalance is checked.
+            let issued = T::StakedToken::deposit_creating(&who, staked_token_issued);
+            ensure!(issued.peek() == staked_token_issued, Error::<T>::MintFailed);
+            TotalMinted::<T>::mutate(|minted| *minted = minted.saturating_add(staked_token_issued));
             Self::deposit_event(Event::StakedTokenIssued(staked_token_issued));
-
-            // Deposit the `StakedToken` token to the user.
-            T::StakedToken::resolve_creating(&who, issued);
             Self::deposit_event(Event::StakedTokenDeposited(
                 who.clone(),
                 staked_token_issued,
             ));
 
-            // Set the block_time in which the operation is performed.
-            let now = <frame_system::Pallet<T>>::block_number();
-            <StakedTimes<T>>::insert(&who, now);
+            // Track the deposit in the ledger of the user. Once it is full of chunks in their
+            // cooldown, the stake is merged into the newest one and restarts its cooldown.
+            Self::mutate_ledger(&who, |ledger| {
+                if ledger
+                    .deposit(staked_token_issued, amount, now, now)
+                    .is_err()
+                {
+                    ledger.merge_into_newest(staked_token_issued, amount, Some(now));
+                }
+                ledger.last_stake = Some(now);
+                Ok(())
+            })?;
 
             Ok(())
         }
 
-        #[pallet::weight(T::DbWeight::get().writes(1))]
-        pub fn unstake(origin: OriginFor<T>, amount: Balance) -> DispatchResult {
+        #[pallet::weight(T::WeightInfo::unstake())]
+        pub fn unstake(origin: OriginFor<T>, amount: BalanceOf<T>) -> DispatchResult {
             // Check that the extrinsic was signed and get the signer.
             // This function will return an error if the extrinsic is not signed.
             let who = ensure_signed(origin)?;
 
-            ensure!(amount > 0, Error::<T>::ZeroAmount);
+            Self::ensure_not_paused(PauseState::UNSTAKE)?;
 
-            ensure!(
-                T::StakedToken::free_balance(&who) >= amount,
-                Error::<T>::NotEnoughStakedToken
-            );
+            ensure!(!amount.is_zero(), Error::<T>::ZeroAmount);
 
-            let last_stake_time = <StakedTimes<T>>::get(&who).unwrap_or_else(|| 0_u8.into());
-            let now = <frame_system::Pallet<T>>::block_number();
+            // A position worth less than `MinStake` must be closed entirely.
+            let remaining = T::StakedToken::free_balance(&who).saturating_sub(amount);
+            if !remaining.is_zero() {
+                let remaining = Self::staked_to_main(remaining).ok_or(ArithmeticError::Overflow)?;
+                ensure!(
+                    remaining >= MinStake::<T>::get(),
+                    Error::<T>::BelowMinimumStake
+                );
+            }
 
-            ensure!(
-                now >= last_stake_time + BlockToUnlock::<T>::get().into(),
-                Error::<T>::TooFastUnstake
-            );
+            let now = <frame_system::Pallet<T>>::block_number();
 
-            // Withdraw the `StakedToken` tokens from the user.
-            let _ = T::StakedToken::withdraw(
-                &who,
-                amount,
-                WithdrawReasons::RESERVE,
-                ExistenceRequirement::KeepAlive,
-            );
+            let redeemed = Self::mutate_ledger(&who, |ledger| {
+                let free = T::StakedToken::free_balance(&who);
+                ensure!(free >= amount, Error::<T>::NotEnoughStakedToken);
+
+                // StakedToken not tracked by the ledger (e.g. received outside of this pallet) has no cooldown.
+                ensure!(
+                    free.saturating_sub(ledger.locked(now)) >= amount,
+                    Error::<T>::TooFastUnstake
+                );
+
+                // The untracked StakedToken is spent first, then the unlocked chunks.
+                // In `CustodyMode::Reserve` nothing the caller reserved backs the untracked
+                // StakedToken, so it can't be redeemed and is never burned.
+                let untracked = match T::Custody::get() {
+                    CustodyMode::Reserve => {
+                        ensure!(amount <= ledger.minted, Error::<T>::UntrackedStakedToken);
+                        ensure!(amount <= ledger.unlocked(now), Error::<T>::TooFastUnstake);
+                        Zero::zero()
+                    }
+                    CustodyMode::PoolAccount => free.saturating_sub(ledger.minted),
+                };
+                let principal = ledger.withdraw_unlocked(amount.saturating_sub(untracked), now);
+
+                // Compute the `MainToken` redeemed before changing the pool.
+                let redeemed = match T::Custody::get() {
+                    // Only the principal reserved by the caller can back its `StakedToken`.
+                    CustodyMode::Reserve => principal,
+                    CustodyMode::PoolAccount => {
+                        Self::staked_to_main(amount).ok_or(ArithmeticError::Overflow)?
+                    }
+                };
+
+                // Withdraw the `StakedToken` tokens from the user, before its shares are updated.
+                // Dropping the returned imbalance burns them, reducing the total issuance.
+                T::StakedToken::withdraw(
+                    &who,
+                    amount,
+                    WithdrawReasons::RESERVE,
+                    ExistenceRequirement::KeepAlive,
+                )
+                .map_err(|_| Error::<T>::WithdrawFailed)?;
+                Ok(redeemed)
+            })?;
+            ensure!(!redeemed.is_zero(), Error::<T>::ZeroAmount);
+
+            // Queue the redeemed `MainToken` tokens, they stay in custody until `withdraw_unbonded`.
+            let unlock_at = now + BlockToUnlock::<T>::get().into();
+            Unbonding::<T>::try_mutate(&who, |chunks| {
+                chunks.try_push(UnbondingChunk {
+                    value: redeemed,
+                    unlock_at,
+                })
+            })
+            .map_err(|_| Error::<T>::TooManyUnbondingChunks)?;
+
+            TotalBurned::<T>::mutate(|burned| *burned = burned.saturating_add(amount));
             Self::deposit_event(Event::StakedTokenWithdrawn(who.clone(), amount));
-
-            // Burn a `value` number StakedToken tokens.
-            let _ = T::StakedToken::burn(amount);
             Self::deposit_event(Event::StakedTokenBurned(amount));
 
-            // Remove the lock from `MainToken` tokens.
-            T::MainToken::unreserve(&who, amount);
-            Self::deposit_event(Event::MainTokenUnstaked(who.clone(), amount));
+            // The redeemed `MainToken` tokens leave the pool.
+            TotalPooled::<T>::mutate(|pooled| *pooled = pooled.saturating_sub(redeemed));
+            Self::deposit_event(Event::UnbondingRequested(who.clone(), redeemed, unlock_at));
+
+            Ok(())
+        }
+
+        #[pallet::weight(T::WeightInfo::withdraw_unbonded())]
+        pub fn withdraw_unbonded(origin: OriginFor<T>) -> DispatchResult {
+            // Check that the extrinsic was signed and get the signer.
+            // This function will return an error if the extrinsic is not signed.
+            let who = ensure_signed(origin)?;
+
+            let now = <frame_system::Pallet<T>>::block_number();
+            let mut chunks = Unbonding::<T>::get(&who);
+            let mut withdrawn: BalanceOf<T> = Zero::zero();
+            chunks.retain(|chunk| {
+                if chunk.unlock_at <= now {
+                    withdrawn = withdrawn.saturating_add(chunk.value);
+                    false
+                } else {
+                    true
+                }
+            });
+
+            ensure!(!withdrawn.is_zero(), Error::<T>::NothingToWithdraw);
+
+            if chunks.is_empty() {
+                Unbonding::<T>::remove(&who);
+            } else {
+                Unbonding::<T>::insert(&who, chunks);
+            }
 
-            // Remove the last_block_time value from the map.
-            <StakedTimes<T>>::remove(&who);
+            // Release the unlocked `MainToken` tokens from the custody of the pool.
+            Self::release_main(&who, withdrawn)?;
+            Self::deposit_event(Event::MainTokenUnstaked(who, withdrawn));
 
             Ok(())
         }
 
-        #[pallet::weight(T::DbWeight::get().writes(1))]
+        #[pallet::weight(T::WeightInfo::transfer())]
         pub fn transfer(
             origin: OriginFor<T>,
             recv: T::AccountId,
-            #[pallet::compact] amount: StakedTokenBalance<T>,
+            #[pallet::compact] amount: BalanceOf<T>,
         ) -> DispatchResult {
             // Check that the extrinsic was signed and get the signer.
             // This function will return an error if the extrinsic is not signed.
             let who = ensure_signed(origin)?;
 
+            Self::ensure_not_paused(PauseState::TRANSFER)?;
+
             ensure!(who != recv, Error::<T>::TransferToSelf);
 
             ensure!(
@@ -261,41 +1181,255 @@ pub mod pallet {
                 Error::<T>::NotEnoughStakedToken
             );
 
-            ensure!(amount > 0, Error::<T>::ZeroAmount);
+            ensure!(!amount.is_zero(), Error::<T>::ZeroAmount);
+
+            // The untracked StakedToken is spent first, then the newest chunks move to `recv`
+            // keeping their cooldown, so a transfer can't be used to skip it.
+            let (moved, principal, since) = Self::mutate_ledger(&who, |sender| {
+                let untracked = T::StakedToken::free_balance(&who).saturating_sub(sender.minted);
+                let moved = sender.withdraw_newest(amount.saturating_sub(untracked));
+
+                // Trasfer the `StakedToken` tokens from who to recv, before the shares of who
+                // are updated.
+                T::StakedToken::transfer(&who, &recv, amount, ExistenceRequirement::KeepAlive)
+                    .map_err(|_| Error::<T>::TransferFailed)?;
+                Ok(moved)
+            })?;
+            if let Some(since) = since {
+                // Reserved `MainToken` backs only the ledger of its owner, so it follows the chunks.
+                if T::Custody::get() == CustodyMode::Reserve && !principal.is_zero() {
+                    let missing = T::MainToken::repatriate_reserved_named(
+                        &Self::reserve_id(),
+                        &who,
+                        &recv,
+                        principal,
+                        BalanceStatus::Reserved,
+                    )
+                    .map_err(|_| Error::<T>::RepatriateFailed)?;
+                    ensure!(missing.is_zero(), Error::<T>::RepatriateFailed);
+                }
+                // The chunks of `recv` keep their cooldown, so it can't be restarted by a sender.
+                // Only a transfer worth `MinStake` can add a chunk to a ledger in use, so filling
+                // the ledger of `recv` with dust costs the sender `MaxDepositChunks` stakes.
+                let now = <frame_system::Pallet<T>>::block_number();
+                let is_dust =
+                    Self::staked_to_main(moved).map_or(false, |value| value < MinStake::<T>::get());
+                Self::mutate_ledger(&recv, |receiver| {
+                    if is_dust && !receiver.chunks.is_empty() {
+                        receiver
+                            .join_later(moved, principal, since)
+                            .map_err(|_| Error::<T>::TransferBelowMinimumStake.into())
+                    } else {
+                        receiver
+                            .deposit(moved, principal, since, now)
+                            .map_err(|_| Error::<T>::TooManyDepositChunks.into())
+                    }
+                })?;
+            }
+
+            Self::deposit_event(Event::StakedTokenTransferred(who, recv, amount));
+
+            Ok(())
+        }
+
+        #[pallet::weight(T::WeightInfo::claim_rewards())]
+        pub fn claim_rewards(origin: OriginFor<T>) -> DispatchResult {
+            // Check that the extrinsic was signed and get the signer.
+            // This function will return an error if the extrinsic is not signed.
+            let who = ensure_signed(origin)?;
+
+            Self::ensure_not_paused(PauseState::REWARDS)?;
+
+            ensure!(
+                !Self::pending_rewards(&who).is_zero(),
+                Error::<T>::NothingToClaim
+            );
+
+            // The rewards are settled by `mutate_ledger`, what is left couldn't be paid.
+            Self::mutate_ledger(&who, |ledger| {
+                ensure!(ledger.unclaimed.is_zero(), Error::<T>::PotInsufficient);
+                Ok(())
+            })
+        }
 
-            // Trasfer the `StakedToken` tokens from who to recv.
-            let _ = T::StakedToken::transfer(&who, &recv, amount, ExistenceRequirement::KeepAlive);
-            Self::deposit_event(Event::StakedTokenTransferred(who.clone(), recv, amount));
+        #[pallet::weight(T::WeightInfo::set_reward_destination())]
+        pub fn set_reward_destination(
+            origin: OriginFor<T>,
+            destination: RewardDestination<T::AccountId>,
+        ) -> DispatchResult {
+            // Check that the extrinsic was signed and get the signer.
+            // This function will return an error if the extrinsic is not signed.
+            let who = ensure_signed(origin)?;
 
-            if T::StakedToken::free_balance(&who) == 0_u32.into() {
-                // Remove the last_block_time value from the map.
-                <StakedTimes<T>>::remove(&who);
+            ensure!(
+                destination != RewardDestination::Account(Self::account_id()),
+                Error::<T>::InvalidRewardDestination
+            );
+            // The reserve backing the rewards can only follow them into the ledger of `who`.
+            ensure!(
+                T::Custody::get() == CustodyMode::PoolAccount
+                    || destination == RewardDestination::Compound,
+                Error::<T>::InvalidRewardDestination
+            );
+
+            // The rewards accrued so far are paid to the old destination.
+            if <Ledger<T>>::contains_key(&who) {
+                Self::mutate_ledger(&who, |_| Ok(()))?;
+            }
+
+            if destination == RewardDestination::Compound {
+                Payee::<T>::remove(&who);
+            } else {
+                Payee::<T>::insert(&who, destination.clone());
             }
+            Self::deposit_event(Event::RewardDestinationSet(who, destination));
 
             Ok(())
         }
 
-        #[pallet::weight(0)]
-        pub fn change_percentage(origin: OriginFor<T>, percentage: u8) -> DispatchResult {
-            // In this way only the ROOT council can call the function!
-            ensure_root(origin)?;
+        #[pallet::weight(T::WeightInfo::fund_pot())]
+        pub fn fund_pot(
+            origin: OriginFor<T>,
+            #[pallet::compact] amount: BalanceOf<T>,
+        ) -> DispatchResult {
+            // Check that the extrinsic was signed and get the signer.
+            // This function will return an error if the extrinsic is not signed.
+            let who = ensure_signed(origin)?;
+
+            ensure!(!amount.is_zero(), Error::<T>::ZeroAmount);
 
-            ensure!(percentage <= 100, Error::<T>::PercentageTooHigh);
+            ensure!(
+                T::MainToken::free_balance(&who) >= amount,
+                Error::<T>::NotEnoughMainToken
+            );
 
-            Percentage::<T>::put(percentage);
+            T::MainToken::transfer(
+                &who,
+                &Self::account_id(),
+                amount,
+                ExistenceRequirement::KeepAlive,
+            )
+            .map_err(|_| Error::<T>::TransferFailed)?;
+            Self::deposit_event(Event::PotFunded(who, amount));
 
             Ok(())
         }
 
-        #[pallet::weight(0)]
+        #[pallet::weight(T::WeightInfo::sweep_pot())]
+        pub fn sweep_pot(
+            origin: OriginFor<T>,
+            to: T::AccountId,
+            #[pallet::compact] amount: BalanceOf<T>,
+        ) -> DispatchResult {
+            // Only the governance can call the function.
+            T::AdminOrigin::ensure_origin(origin)?;
+
+            ensure!(!amount.is_zero(), Error::<T>::ZeroAmount);
+
+            // The rewards already distributed are StakedToken backed by the custody of the pool,
+            // so only the MainToken not distributed yet can be swept.
+            let pot_address = Self::account_id();
+            ensure!(
+                amount <= Self::pot_balance(),
+                Error::<T>::SweepExceedsSurplus
+            );
+
+            T::MainToken::transfer(&pot_address, &to, amount, ExistenceRequirement::KeepAlive)
+                .map_err(|_| Error::<T>::TransferFailed)?;
+            Self::deposit_event(Event::PotSwept(to, amount));
+
+            Ok(())
+        }
+
+        #[pallet::weight(T::WeightInfo::change_percentage())]
+        pub fn change_percentage(origin: OriginFor<T>, percentage: Perbill) -> DispatchResult {
+            // Only the governance can call the function.
+            T::AdminOrigin::ensure_origin(origin)?;
+
+            Self::schedule_parameter_change(ParameterChange::Percentage(percentage))
+        }
+
+        #[pallet::weight(T::WeightInfo::change_block_time())]
         pub fn change_block_time(
             origin: OriginFor<T>,
             #[pallet::compact] block_time: u32,
         ) -> DispatchResult {
-            // In this way only the ROOT council can call the function!
-            ensure_root(origin)?;
+            // Only the governance can call the function.
+            T::AdminOrigin::ensure_origin(origin)?;
+
+            Self::schedule_parameter_change(ParameterChange::BlockToUnlock(block_time))
+        }
+
+        #[pallet::weight(T::WeightInfo::change_reward_period())]
+        pub fn change_reward_period(
+            origin: OriginFor<T>,
+            reward_period: T::BlockNumber,
+        ) -> DispatchResult {
+            // Only the governance can call the function.
+            T::AdminOrigin::ensure_origin(origin)?;
+
+            Self::schedule_parameter_change(ParameterChange::RewardPeriod(reward_period))
+        }
+
+        #[pallet::weight(T::WeightInfo::change_stake_limits())]
+        pub fn change_stake_limits(
+            origin: OriginFor<T>,
+            min_stake: BalanceOf<T>,
+            max_stake_per_account: BalanceOf<T>,
+            max_total_staked: BalanceOf<T>,
+        ) -> DispatchResult {
+            // Only the governance can call the function.
+            T::AdminOrigin::ensure_origin(origin)?;
+
+            Self::schedule_parameter_change(ParameterChange::StakeLimits {
+                min_stake,
+                max_stake_per_account,
+                max_total_staked,
+            })
+        }
+
+        #[pallet::weight(T::WeightInfo::cancel_parameter_change())]
+        pub fn cancel_parameter_change(origin: OriginFor<T>, id: u32) -> DispatchResult {
+            // Only the governance can call the function.
+            T::AdminOrigin::ensure_origin(origin)?;
+
+            PendingChanges::<T>::try_mutate(|pending| {
+                let index = pending
+                    .iter()
+                    .position(|pending| pending.id == id)
+                    .ok_or(Error::<T>::UnknownParameterChange)?;
+                pending.remove(index);
+                Ok::<_, DispatchError>(())
+            })?;
+            Self::deposit_event(Event::ParameterChangeCancelled(id));
+
+            Ok(())
+        }
+
+        #[pallet::weight(T::WeightInfo::pause())]
+        pub fn pause(origin: OriginFor<T>, ops: PauseState) -> DispatchResult {
+            T::EmergencyOrigin::ensure_origin(origin)?;
+            ensure!(ops.is_valid(), Error::<T>::InvalidPauseState);
+
+            let state = Paused::<T>::mutate(|state| {
+                state.insert(ops);
+                *state
+            });
+            Self::deposit_event(Event::OperationsPaused(ops, state));
 
-            BlockToUnlock::<T>::put(block_time);
+            Ok(())
+        }
+
+        #[pallet::weight(T::WeightInfo::unpause())]
+        pub fn unpause(origin: OriginFor<T>, ops: PauseState) -> DispatchResult {
+            T::EmergencyOrigin::ensure_origin(origin)?;
+            ensure!(ops.is_valid(), Error::<T>::InvalidPauseState);
+
+            let state = Paused::<T>::mutate(|state| {
+                state.remove(ops);
+                *state
+            });
+            Self::deposit_event(Event::OperationsUnpaused(ops, state));
 
             Ok(())
         }
@@ -303,36 +1437,770 @@ pub mod pallet {
 
     #[pallet::hooks]
     impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+        fn on_initialize(n: T::BlockNumber) -> Weight {
+            let enacted = Self::enact_parameter_changes(n);
+
+            // The weight of the enacted changes and of `on_finalize`,
+            // whose reward distribution doesn't depend on the number of stakers.
+            let weight = T::WeightInfo::on_initialize_enact(enacted)
+                .saturating_add(T::WeightInfo::on_finalize_rewards(1));
+            if T::CheckInvariants::get() {
+                weight.saturating_add(T::WeightInfo::check_invariants())
+            } else {
+                weight
+            }
+        }
+
+        fn on_runtime_upgrade() -> Weight {
+            migrations::v1::MigrateToV1::<T>::on_runtime_upgrade()
+        }
+
+        #[cfg(feature = "try-runtime")]
+        fn pre_upgrade() -> Result<(), &'static str> {
+            migrations::v1::MigrateToV1::<T>::pre_upgrade()
+        }
+
+        #[cfg(feature = "try-runtime")]
+        fn post_upgrade() -> Result<(), &'static str> {
+            migrations::v1::MigrateToV1::<T>::post_upgrade()?;
+            Self::do_try_state()
+        }
+
+        fn integrity_test() {
+            assert!(
+                T::MinBlockToUnlock::get() <= T::MaxBlockToUnlock::get(),
+                "`MinBlockToUnlock` must not be above `MaxBlockToUnlock`"
+            );
+            assert!(
+                (T::MinBlockToUnlock::get()..=T::MaxBlockToUnlock::get())
+                    .contains(&DefaultBlockTime::<T>::get()),
+                "the default `BlockToUnlock` must be between `MinBlockToUnlock` and `MaxBlockToUnlock`"
+            );
+            assert!(
+                DefaultPercentage::<T>::get() <= T::MaxRewardRate::get(),
+                "the default `Percentage` must not be above `MaxRewardRate`"
+            );
+            assert!(
+                T::MaxDepositChunks::get() > 0,
+                "`MaxDepositChunks` must allow tracking a deposit"
+            );
+            assert!(
+                !T::MaxStepChange::get().is_zero(),
+                "`MaxStepChange` must allow changing the percentage"
+            );
+            assert!(
+                T::DefaultMinStake::get() <= T::DefaultMaxStakePerAccount::get(),
+                "`DefaultMinStake` must not be above `DefaultMaxStakePerAccount`"
+            );
+            assert!(
+                !T::DefaultRewardPeriod::get().is_zero()
+                    && T::DefaultRewardPeriod::get() <= T::MaxRewardPeriod::get(),
+                "`DefaultRewardPeriod` must be between 1 and `MaxRewardPeriod`"
+            );
+        }
+
         fn on_finalize(n: T::BlockNumber) {
-            // 5 blocks are hardcoded for simplification but can be any value chosen by the governance
-            if n % 5u8.into() == frame_support::sp_runtime::traits::Zero::zero() {
-                for who in <StakedTimes<T>>::iter_keys() {
-                    if <StakedTimes<T>>::get(&who).is_some() {
-                        let pot_address = Self::account_id();
-                        let amount = T::StakedToken::free_balance(&who);
-                        let percentage = Percentage::<T>::get().into();
-                        // Use checked math to perform operations, if something goes wrong, send 0 tokens.
-                        let gift = amount
-                            .checked_div(100)
-                            .unwrap_or(0)
-                            .checked_mul(percentage)
-                            .unwrap_or(0);
-                        let _ = T::StakedToken::transfer(
-                            &pot_address,
-                            &who,
-                            gift,
-                            ExistenceRequirement::KeepAlive,
-                        );
-                        Self::deposit_event(Event::StakedTokenTransferred(pot_address, who, gift));
-                    }
+            // Distribute once every `RewardPeriod` blocks, counting from the last distribution.
+            // If the period was shortened below the blocks already elapsed, distribute now.
+            if n.saturating_sub(LastRewardBlock::<T>::get()) >= RewardPeriod::<T>::get() {
+                // The distributions due while the rewards are paused are skipped.
+                if !Self::is_paused(PauseState::REWARDS) {
+                    Self::distribute_rewards();
                 }
+                LastRewardBlock::<T>::put(n);
             };
+
+            if T::CheckInvariants::get() {
+                Self::enforce_invariants();
+            }
         }
     }
 
     impl<T: Config> Pallet<T> {
+        /// Whether all the operations in `ops` are paused.
+        pub fn is_paused(ops: PauseState) -> bool {
+            Paused::<T>::get().contains(ops)
+        }
+
+        /// Fail with `Paused` if any of the operations in `ops` is paused.
+        fn ensure_not_paused(ops: PauseState) -> DispatchResult {
+            ensure!(!Paused::<T>::get().intersects(ops), Error::<T>::Paused);
+            Ok(())
+        }
+
+        /// Check `change` and queue it until `ParameterChangeDelay` blocks have passed.
+        /// Without a delay it is applied immediately.
+        fn schedule_parameter_change(
+            change: ParameterChange<BalanceOf<T>, T::BlockNumber>,
+        ) -> DispatchResult {
+            // The step is measured from the value in effect once the pending changes apply.
+            let percentage = Self::pending_changes()
+                .iter()
+                .rev()
+                .find_map(|pending| match pending.change {
+                    ParameterChange::Percentage(percentage) => Some(percentage),
+                    _ => None,
+                })
+                .unwrap_or_else(Percentage::<T>::get);
+            Self::ensure_valid_parameter_change(&change, percentage)?;
+
+            let id = NextChangeId::<T>::get();
+            NextChangeId::<T>::put(id.wrapping_add(1));
+            let delay = T::ParameterChangeDelay::get();
+            let enact_at = <frame_system::Pallet<T>>::block_number().saturating_add(delay);
+            Self::deposit_event(Event::ParameterChangeScheduled(
+                id,
+                change.clone(),
+                enact_at,
+            ));
+
+            if delay.is_zero() {
+                Self::apply_parameter_change(id, change);
+                return Ok(());
+            }
+            PendingChanges::<T>::try_mutate(|pending| {
+                pending.try_push(PendingChange {
+                    id,
+                    change,
+                    enact_at,
+                })
+            })
+            .map_err(|_| Error::<T>::TooManyPendingChanges)?;
+
+            Ok(())
+        }
+
+        /// Check that `change` is within the bounds of the pool,
+        /// with the step of a new percentage measured from `current`.
+        fn ensure_valid_parameter_change(
+            change: &ParameterChange<BalanceOf<T>, T::BlockNumber>,
+            current: Perbill,
+        ) -> DispatchResult {
+            match change {
+                ParameterChange::Percentage(percentage) => {
+                    ensure!(
+                        *percentage <= T::MaxRewardRate::get(),
+                        Error::<T>::PercentageTooHigh
+                    );
+                    let (low, high) = if *percentage < current {
+                        (*percentage, current)
+                    } else {
+                        (current, *percentage)
+                    };
+                    ensure!(
+                        high.saturating_sub(low) <= T::MaxStepChange::get(),
+                        Error::<T>::StepChangeTooLarge
+                    );
+                }
+                ParameterChange::BlockToUnlock(block_time) => {
+                    ensure!(
+                        (T::MinBlockToUnlock::get()..=T::MaxBlockToUnlock::get())
+                            .contains(block_time),
+                        Error::<T>::BlockToUnlockOutOfBounds
+                    );
+                }
+                ParameterChange::RewardPeriod(reward_period) => {
+                    ensure!(
+                        !reward_period.is_zero() && *reward_period <= T::MaxRewardPeriod::get(),
+                        Error::<T>::RewardPeriodOutOfBounds
+                    );
+                }
+                ParameterChange::StakeLimits {
+                    min_stake,
+                    max_stake_per_account,
+                    ..
+                } => {
+                    ensure!(
+                        min_stake <= max_stake_per_account,
+                        Error::<T>::InvalidStakeLimits
+                    );
+                }
+            }
+
+            Ok(())
+        }
+
+        /// Apply the pending changes whose timelock expired at block `now`.
+        /// Each change is checked again against the values in effect, since cancelling a change
+        /// can leave a later one more than `MaxStepChange` away from the live percentage.
+        /// Returns the number of enacted or dropped changes.
+        fn enact_parameter_changes(now: T::BlockNumber) -> u32 {
+            let mut due = Vec::new();
+            PendingChanges::<T>::mutate(|pending| {
+                pending.retain(|pending| {
+                    if pending.enact_at <= now {
+                        due.push((pending.id, pending.change.clone()));
+                        false
+                    } else {
+                        true
+                    }
+                })
+            });
+
+            let enacted = due.len() as u32;
+            for (id, change) in due {
+                match Self::ensure_valid_parameter_change(&change, Percentage::<T>::get()) {
+                    Ok(()) => Self::apply_parameter_change(id, change),
+                    Err(error) => Self::deposit_event(Event::ParameterChangeDropped(id, error)),
+                }
+            }
+            enacted
+        }
+
+        /// Set the parameter changed by `change`, emitting the old and the new value.
+        fn apply_parameter_change(id: u32, change: ParameterChange<BalanceOf<T>, T::BlockNumber>) {
+            match change {
+                ParameterChange::Percentage(percentage) => {
+                    let old =
+                        Percentage::<T>::mutate(|current| core::mem::replace(current, percentage));
+                    Self::deposit_event(Event::PercentageChanged(old, percentage));
+                }
+                ParameterChange::BlockToUnlock(block_time) => {
+                    let old = BlockToUnlock::<T>::mutate(|current| {
+                        core::mem::replace(current, block_time)
+                    });
+                    Self::deposit_event(Event::BlockToUnlockChanged(old, block_time));
+                }
+                ParameterChange::RewardPeriod(reward_period) => {
+                    // The cycle in progress is not reset: the next distribution happens
+                    // `reward_period` blocks after the last one, so no distribution is paid
+                    // twice or skipped.
+                    let old = RewardPeriod::<T>::mutate(|current| {
+                        core::mem::replace(current, reward_period)
+                    });
+                    Self::deposit_event(Event::RewardPeriodChanged(old, reward_period));
+                }
+                ParameterChange::StakeLimits {
+                    min_stake,
+                    max_stake_per_account,
+                    max_total_staked,
+                } => {
+                    // Existing positions are not affected until they change.
+                    MinStake::<T>::put(min_stake);
+                    MaxStakePerAccount::<T>::put(max_stake_per_account);
+                    MaxTotalStaked::<T>::put(max_total_staked);
+                    Self::deposit_event(Event::StakeLimitsChanged(
+                        min_stake,
+                        max_stake_per_account,
+                        max_total_staked,
+                    ));
+                }
+            }
+            Self::deposit_event(Event::ParameterChangeEnacted(id, change));
+        }
+
         pub fn account_id() -> T::AccountId {
             T::PalletId::get().into_account_truncating()
         }
+
+        /// The account holding the pooled MainToken in `CustodyMode::PoolAccount`.
+        pub fn pool_account() -> T::AccountId {
+            T::PalletId::get().into_sub_account_truncating(b"pool")
+        }
+
+        /// The identifier of the MainToken reserved in `CustodyMode::Reserve`.
+        pub fn reserve_id() -> [u8; 8] {
+            T::PalletId::get().0
+        }
+
+        /// Move `amount` MainToken from `who` into the custody of the pool.
+        fn lock_main(who: &T::AccountId, amount: BalanceOf<T>) -> DispatchResult {
+            match T::Custody::get() {
+                CustodyMode::Reserve => {
+                    T::MainToken::reserve_named(&Self::reserve_id(), who, amount)
+                        .map_err(|_| Error::<T>::ReserveFailed)?
+                }
+                CustodyMode::PoolAccount => T::MainToken::transfer(
+                    who,
+                    &Self::pool_account(),
+                    amount,
+                    ExistenceRequirement::KeepAlive,
+                )
+                .map_err(|_| Error::<T>::TransferFailed)?,
+            }
+            Ok(())
+        }
+
+        /// Release `amount` MainToken from the custody of the pool to `who`.
+        fn release_main(who: &T::AccountId, amount: BalanceOf<T>) -> DispatchResult {
+            match T::Custody::get() {
+                CustodyMode::Reserve => {
+                    // Only the named reserve of the pool is released, never other reserves of `who`.
+                    let missing = T::MainToken::unreserve_named(&Self::reserve_id(), who, amount);
+                    ensure!(missing.is_zero(), Error::<T>::UnreserveFailed);
+                }
+                CustodyMode::PoolAccount => T::MainToken::transfer(
+                    &Self::pool_account(),
+                    who,
+                    amount,
+                    ExistenceRequirement::KeepAlive,
+                )
+                .map_err(|_| Error::<T>::TransferFailed)?,
+            }
+            Ok(())
+        }
+
+        /// The StakedToken rewards accrued by `who` and not paid yet.
+        pub fn pending_rewards(who: &T::AccountId) -> BalanceOf<T> {
+            <Ledger<T>>::get(who)
+                .map(|ledger| {
+                    ledger
+                        .unclaimed
+                        .saturating_add(Self::accrued_rewards(who, &ledger).0)
+                })
+                .unwrap_or_else(Zero::zero)
+        }
+
+        /// The StakedToken rewards accrued by `ledger` since it was last stored.
+        /// Rounded down in favour of the pool: the dust stays in the pot.
+        /// The shares whose StakedToken left the account outside of this pallet earn nothing:
+        /// the second value is the part of the rewards they forfeit.
+        fn accrued_rewards(
+            who: &T::AccountId,
+            ledger: &StakingLedger<T>,
+        ) -> (BalanceOf<T>, BalanceOf<T>) {
+            let accrued = RewardPerShare::<T>::get()
+                .saturating_mul_int(ledger.shares)
+                .saturating_sub(RewardDebt::<T>::get(who));
+            let held = ledger.shares.min(T::StakedToken::free_balance(who));
+            let earned = mul_div(accrued, held, ledger.shares).unwrap_or(accrued);
+            (earned, accrued.saturating_sub(earned))
+        }
+
+        /// Settle the rewards of `who`, apply `f` to its ledger and store it,
+        /// keeping `TotalShares` and the reward debt in sync with the new shares.
+        /// The StakedToken moved by `f` must already be in or out of the account,
+        /// as the shares are capped at its balance.
+        fn mutate_ledger<R>(
+            who: &T::AccountId,
+            f: impl FnOnce(&mut StakingLedger<T>) -> Result<R, DispatchError>,
+        ) -> Result<R, DispatchError> {
+            let mut ledger = <Ledger<T>>::get(who).unwrap_or_default();
+            let shares = ledger.shares;
+
+            Self::settle_rewards(who, &mut ledger);
+            let result = f(&mut ledger)?;
+
+            ledger.shares = ledger.minted.min(T::StakedToken::free_balance(who));
+            TotalShares::<T>::mutate(|total| {
+                *total = total.saturating_sub(shares).saturating_add(ledger.shares)
+            });
+            if ledger.chunks.is_empty() && ledger.unclaimed.is_zero() {
+                <Ledger<T>>::remove(who);
+                RewardDebt::<T>::remove(who);
+            } else {
+                let debt = RewardPerShare::<T>::get().saturating_mul_int(ledger.shares);
+                RewardDebt::<T>::insert(who, debt);
+                <Ledger<T>>::insert(who, ledger);
+            }
+
+            Ok(result)
+        }
+
+        /// Pay the rewards accrued by `ledger` from the pot to the `Payee` of `who`.
+        /// Compounded rewards are tracked as a new deposit, what the pot can't cover is kept in `unclaimed`.
+        /// While the rewards are paused nothing is paid, the rewards are kept in `unclaimed`.
+        fn settle_rewards(who: &T::AccountId, ledger: &mut StakingLedger<T>) {
+            let (earned, forfeited) = Self::accrued_rewards(who, ledger);
+            // The forfeited rewards are no longer owed, they stay in the pot.
+            OwedRewards::<T>::mutate(|owed| *owed = owed.saturating_sub(forfeited));
+            ledger.unclaimed = ledger.unclaimed.saturating_add(earned);
+            if ledger.unclaimed.is_zero() || Self::is_paused(PauseState::REWARDS) {
+                return;
+            }
+
+            let reward = ledger.unclaimed;
+            // In `CustodyMode::Reserve` the reserve backing the reward must stay in the ledger.
+            let destination = match T::Custody::get() {
+                CustodyMode::Reserve => RewardDestination::Compound,
+                CustodyMode::PoolAccount => Payee::<T>::get(who),
+            };
+            let recipient = match &destination {
+                RewardDestination::Account(recipient) => recipient.clone(),
+                RewardDestination::Compound | RewardDestination::Free => who.clone(),
+            };
+            match with_storage_layer(|| Self::pay_reward(who, &recipient, reward)) {
+                Ok(principal) => {
+                    if destination == RewardDestination::Compound {
+                        // The reward is a new deposit. In a full ledger it joins the newest
+                        // chunk without restarting its cooldown.
+                        let now = <frame_system::Pallet<T>>::block_number();
+                        if ledger.deposit(reward, principal, now, now).is_err() {
+                            ledger.merge_into_newest(reward, principal, None);
+                        }
+                    }
+                    ledger.unclaimed = Zero::zero();
+                    Self::deposit_event(Event::RewardPaid(who.clone(), destination, reward));
+                }
+                Err(error) => Self::deposit_event(Event::RewardSkipped(who.clone(), reward, error)),
+            }
+        }
+
+        /// Every share earns `Percentage` of itself. The rewards are only accounted here
+        /// and settled lazily, so the cost doesn't depend on the number of stakers.
+        /// If the pot can't cover the whole distribution, the rewards are scaled down pro-rata.
+        fn distribute_rewards() {
+            let total_shares = TotalShares::<T>::get();
+            if total_shares.is_zero() {
+                return;
+            }
+
+            // The StakedToken left in the pot by forfeited rewards is distributed first,
+            // then the MainToken of the pot backs new StakedToken.
+            let pot_address = Self::account_id();
+            let surplus =
+                T::StakedToken::free_balance(&pot_address).saturating_sub(OwedRewards::<T>::get());
+            let mut reward_per_share = FixedU128::from(Percentage::<T>::get());
+            let required = reward_per_share.saturating_mul_int(total_shares);
+            let available = Self::main_to_staked(Self::pot_balance())
+                .unwrap_or_else(Zero::zero)
+                .saturating_add(surplus);
+
+            if required > available {
+                reward_per_share = FixedU128::saturating_from_rational(available, total_shares);
+                if !PotDepletionReported::<T>::get() {
+                    PotDepletionReported::<T>::put(true);
+                    Self::deposit_event(Event::PotDepleted(available, required));
+                }
+            } else {
+                PotDepletionReported::<T>::kill();
+            }
+
+            // Up to rounding dust, the accounts settle exactly what is distributed here.
+            let distributed = reward_per_share.saturating_mul_int(total_shares);
+            let minted = distributed.saturating_sub(surplus);
+            if !minted.is_zero() && with_storage_layer(|| Self::mint_rewards(minted)).is_err() {
+                return;
+            }
+            OwedRewards::<T>::mutate(|owed| *owed = owed.saturating_add(distributed));
+            RewardPerShare::<T>::mutate(|acc| *acc = acc.saturating_add(reward_per_share));
+        }
+
+        /// The MainToken of the pot available for the rewards. The pot keeps the existential
+        /// deposit, so it is never reaped together with the reserve backing the owed rewards.
+        pub fn pot_balance() -> BalanceOf<T> {
+            T::MainToken::free_balance(&Self::account_id())
+                .saturating_sub(T::MainToken::minimum_balance())
+        }
+
+        /// Mint `amount` StakedToken into the pot to pay the rewards, backed by the MainToken
+        /// it is worth moved from the pot into the custody of the pool, so the exchange rate
+        /// doesn't change. In `CustodyMode::Reserve` the MainToken is reserved on the pot,
+        /// until the rewards are paid.
+        fn mint_rewards(amount: BalanceOf<T>) -> DispatchResult {
+            // Rounded up in favour of the pool.
+            let (pooled, supply) = Self::pool_ratio();
+            let backing = mul_div_up(amount, pooled, supply).ok_or(ArithmeticError::Overflow)?;
+
+            let pot_address = Self::account_id();
+            match T::Custody::get() {
+                CustodyMode::Reserve => {
+                    T::MainToken::reserve_named(&Self::reserve_id(), &pot_address, backing)
+                        .map_err(|_| Error::<T>::ReserveFailed)?
+                }
+                CustodyMode::PoolAccount => T::MainToken::transfer(
+                    &pot_address,
+                    &Self::pool_account(),
+                    backing,
+                    ExistenceRequirement::KeepAlive,
+                )
+                .map_err(|_| Error::<T>::TransferFailed)?,
+            }
+            TotalPooled::<T>::mutate(|pooled| *pooled = pooled.saturating_add(backing));
+
+            let issued = T::StakedToken::deposit_creating(&pot_address, amount);
+            ensure!(issued.peek() == amount, Error::<T>::MintFailed);
+            TotalMinted::<T>::mutate(|minted| *minted = minted.saturating_add(amount));
+            Self::deposit_event(Event::StakedTokenIssued(amount));
+
+            Ok(())
+        }
+
+        /// Pay `reward` StakedToken from the pot to `recipient`, on behalf of `owner`.
+        /// In `CustodyMode::Reserve` the MainToken backing the reward moves to the reserve of
+        /// `owner`, and is returned as the principal of the reward.
+        fn pay_reward(
+            owner: &T::AccountId,
+            recipient: &T::AccountId,
+            reward: BalanceOf<T>,
+        ) -> Result<BalanceOf<T>, DispatchError> {
+            let pot_address = Self::account_id();
+            let pot = T::StakedToken::free_balance(&pot_address);
+            ensure!(pot >= reward, Error::<T>::PotInsufficient);
+
+            let principal = match T::Custody::get() {
+                CustodyMode::Reserve => {
+                    let reserve_id = Self::reserve_id();
+                    let backing = T::MainToken::reserved_balance_named(&reserve_id, &pot_address);
+                    let principal =
+                        mul_div(backing, reward, pot).ok_or(ArithmeticError::Overflow)?;
+                    let missing = T::MainToken::repatriate_reserved_named(
+                        &reserve_id,
+                        &pot_address,
+                        owner,
+                        principal,
+                        BalanceStatus::Reserved,
+                    )
+                    .map_err(|_| Error::<T>::RepatriateFailed)?;
+                    ensure!(missing.is_zero(), Error::<T>::RepatriateFailed);
+                    principal
+                }
+                CustodyMode::PoolAccount => Zero::zero(),
+            };
+
+            T::StakedToken::transfer(
+                &pot_address,
+                recipient,
+                reward,
+                ExistenceRequirement::KeepAlive,
+            )
+            .map_err(|_| Error::<T>::TransferFailed)?;
+            Self::deposit_event(Event::StakedTokenTransferred(
+                pot_address,
+                recipient.clone(),
+                reward,
+            ));
+            OwedRewards::<T>::mutate(|owed| *owed = owed.saturating_sub(reward));
+            TotalRewardsPaid::<T>::mutate(|paid| *paid = paid.saturating_add(reward));
+
+            Ok(principal)
+        }
+
+        /// The MainToken the StakedToken of `who` can be redeemed for.
+        fn position_value(who: &T::AccountId) -> Result<BalanceOf<T>, DispatchError> {
+            Self::staked_to_main(T::StakedToken::free_balance(who))
+                .ok_or_else(|| ArithmeticError::Overflow.into())
+        }
+
+        /// The amount of StakedToken in circulation, i.e. its total issuance.
+        /// The rewards owed by the pot are included, as they are backed like any other StakedToken.
+        pub fn staked_supply() -> BalanceOf<T> {
+            T::StakedToken::total_issuance()
+        }
+
+        /// The MainToken a single StakedToken can be redeemed for.
+        /// An empty pool starts with a 1:1 rate.
+        pub fn exchange_rate() -> FixedU128 {
+            let (pooled, supply) = Self::pool_ratio();
+            FixedU128::saturating_from_rational(pooled, supply)
+        }
+
+        /// The exchange rate of the pool as a `(pooled, supply)` pair:
+        /// `supply` StakedToken can be redeemed for `pooled` MainToken.
+        fn pool_ratio() -> (BalanceOf<T>, BalanceOf<T>) {
+            let pooled = TotalPooled::<T>::get();
+            let supply = Self::staked_supply();
+            if pooled.is_zero() || supply.is_zero() {
+                (One::one(), One::one())
+            } else {
+                (pooled, supply)
+            }
+        }
+
+        /// The amount of StakedToken minted for `amount` MainToken at the current exchange rate,
+        /// rounded down in favour of the pool.
+        pub fn main_to_staked(amount: BalanceOf<T>) -> Option<BalanceOf<T>> {
+            let (pooled, supply) = Self::pool_ratio();
+            mul_div(amount, supply, pooled)
+        }
+
+        /// The amount of MainToken redeemed for `amount` StakedToken at the current exchange rate,
+        /// rounded down in favour of the pool.
+        pub fn staked_to_main(amount: BalanceOf<T>) -> Option<BalanceOf<T>> {
+            let (pooled, supply) = Self::pool_ratio();
+            mul_div(amount, pooled, supply)
+        }
+
+        /// The StakedToken `stake(amount)` would mint now, `None` on overflow.
+        /// The limits of the pool and of the caller are not checked.
+        pub fn preview_stake(amount: BalanceOf<T>) -> Option<BalanceOf<T>> {
+            Self::main_to_staked(amount)
+        }
+
+        /// The MainToken `unstake(amount)` would queue for unbonding now, `None` on overflow.
+        /// In `CustodyMode::Reserve` the caller can't redeem more than the principal in its ledger.
+        pub fn preview_unstake(amount: BalanceOf<T>) -> Option<BalanceOf<T>> {
+            Self::staked_to_main(amount)
+        }
+
+        /// The first block in which a deposit of `who` still in its cooldown unlocks,
+        /// `None` if all its deposits are unlocked.
+        pub fn next_unlock_block(who: &T::AccountId) -> Option<T::BlockNumber> {
+            let now = <frame_system::Pallet<T>>::block_number();
+            let cooldown: T::BlockNumber = BlockToUnlock::<T>::get().into();
+            <Ledger<T>>::get(who)?
+                .chunks
+                .iter()
+                .map(|chunk| chunk.since + cooldown)
+                .filter(|unlock_at| *unlock_at > now)
+                .min()
+        }
+
+        /// Check the invariants that only read a few storage values, returning the first one
+        /// that fails.
+        pub fn check_invariants() -> Option<Invariant> {
+            if T::StakedToken::free_balance(&Self::account_id()) < OwedRewards::<T>::get() {
+                return Some(Invariant::PotBelowOwedRewards);
+            }
+            if T::Custody::get() == CustodyMode::PoolAccount
+                && T::MainToken::free_balance(&Self::pool_account()) < TotalPooled::<T>::get()
+            {
+                return Some(Invariant::CustodyBelowPooled);
+            }
+            let issuance = T::StakedToken::total_issuance();
+            if issuance < TotalShares::<T>::get() {
+                return Some(Invariant::IssuanceBelowShares);
+            }
+            if issuance > TotalMinted::<T>::get().saturating_sub(TotalBurned::<T>::get()) {
+                return Some(Invariant::IssuanceAboveMinted);
+            }
+            None
+        }
+
+        /// Pause staking if one of the invariants of `check_invariants` fails.
+        /// Nothing is checked while staking is paused, so the violation is reported once.
+        pub(crate) fn enforce_invariants() {
+            if Self::is_paused(PauseState::STAKE) {
+                return;
+            }
+            if let Some(invariant) = Self::check_invariants() {
+                let state = Paused::<T>::mutate(|state| {
+                    state.insert(PauseState::STAKE);
+                    *state
+                });
+                Self::deposit_event(Event::OperationsPaused(PauseState::STAKE, state));
+                Self::deposit_event(Event::InvariantViolated(invariant));
+            }
+        }
+
+        /// Check all the invariants of the pool, iterating over every ledger.
+        /// Too expensive for a block, it is meant for `try-runtime` and tests.
+        #[cfg(any(feature = "try-runtime", test))]
+        pub fn do_try_state() -> Result<(), &'static str> {
+            if let Some(invariant) = Self::check_invariants() {
+                return Err(match invariant {
+                    Invariant::PotBelowOwedRewards => "the pot is below the owed rewards",
+                    Invariant::CustodyBelowPooled => "the pool account is below `TotalPooled`",
+                    Invariant::IssuanceBelowShares => {
+                        "the StakedToken issuance is below the shares"
+                    }
+                    Invariant::IssuanceAboveMinted => {
+                        "the StakedToken issuance is above the minted and not burned StakedToken"
+                    }
+                });
+            }
+            // Only the pool mints and burns StakedToken, so nothing else is missing either.
+            ensure!(
+                T::StakedToken::total_issuance()
+                    == TotalMinted::<T>::get().saturating_sub(TotalBurned::<T>::get()),
+                "the StakedToken issuance doesn't match the minted and not burned StakedToken"
+            );
+
+            let mut shares: BalanceOf<T> = Zero::zero();
+            let mut minted: BalanceOf<T> = Zero::zero();
+            let mut principal: BalanceOf<T> = Zero::zero();
+            let mut pending: BalanceOf<T> = Zero::zero();
+            let mut stakers = BTreeSet::new();
+            for (who, ledger) in <Ledger<T>>::iter() {
+                let chunks = ledger
+                    .chunks
+                    .iter()
+                    .fold(Zero::zero(), |acc: BalanceOf<T>, chunk| {
+                        acc.saturating_add(chunk.value)
+                    });
+                ensure!(
+                    chunks == ledger.minted,
+                    "the chunks of a ledger don't add up to its minted StakedToken"
+                );
+                ensure!(
+                    ledger.shares <= ledger.minted,
+                    "the shares of a ledger are above its minted StakedToken"
+                );
+                shares = shares.saturating_add(ledger.shares);
+                minted = minted.saturating_add(ledger.minted);
+                principal = principal.saturating_add(ledger.principal);
+                pending = pending.saturating_add(Self::pending_rewards(&who));
+                stakers.insert(who);
+            }
+            ensure!(
+                shares == TotalShares::<T>::get(),
+                "the ledgers don't add up to `TotalShares`"
+            );
+            // Each ledger rounds its rewards on its own, so it can be owed one more unit.
+            let rounding: BalanceOf<T> = (stakers.len() as u32).into();
+            ensure!(
+                pending <= OwedRewards::<T>::get().saturating_add(rounding),
+                "the pending rewards are above the owed rewards"
+            );
+
+            let mut unbonding: BalanceOf<T> = Zero::zero();
+            for (who, chunks) in Unbonding::<T>::iter() {
+                unbonding = chunks
+                    .iter()
+                    .fold(unbonding, |acc, chunk| acc.saturating_add(chunk.value));
+                stakers.insert(who);
+            }
+            let custody = TotalPooled::<T>::get().saturating_add(unbonding);
+
+            match T::Custody::get() {
+                CustodyMode::Reserve => {
+                    // The rewards owed by the pot are backed by its own reserve.
+                    let reserve_id = Self::reserve_id();
+                    let pot =
+                        T::MainToken::reserved_balance_named(&reserve_id, &Self::account_id());
+                    ensure!(
+                        principal.saturating_add(pot) == TotalPooled::<T>::get(),
+                        "the ledgers and the pot don't add up to `TotalPooled`"
+                    );
+                    let reserved = stakers.iter().fold(Zero::zero(), |acc: BalanceOf<T>, who| {
+                        acc.saturating_add(T::MainToken::reserved_balance_named(&reserve_id, who))
+                    });
+                    ensure!(
+                        reserved.saturating_add(pot) == custody,
+                        "the reserves don't match the pooled and unbonding MainToken"
+                    );
+                }
+                CustodyMode::PoolAccount => {
+                    // Any holder redeems against the pool at the exchange rate, so the ledgers
+                    // are worth at most `TotalPooled` as long as they track issued StakedToken.
+                    ensure!(
+                        minted <= Self::staked_supply(),
+                        "the ledgers track more StakedToken than the staked supply"
+                    );
+                    ensure!(
+                        T::MainToken::free_balance(&Self::pool_account()) >= custody,
+                        "the pool account is below the pooled and unbonding MainToken"
+                    );
+                }
+            }
+
+            Ok(())
+        }
+
+        /// The staking position of `who`.
+        pub fn position(who: &T::AccountId) -> Position<BalanceOf<T>> {
+            let now = <frame_system::Pallet<T>>::block_number();
+            let staked = T::StakedToken::free_balance(who);
+            let ledger = <Ledger<T>>::get(who).unwrap_or_default();
+            Position {
+                staked,
+                // The StakedToken of an account is part of the supply, so its value can't overflow.
+                value: Self::staked_to_main(staked).unwrap_or_else(Zero::zero),
+                principal: ledger.principal,
+                locked: ledger.locked(now),
+                pending_rewards: Self::pending_rewards(who),
+            }
+        }
+
+        /// The totals of the pool and of its reward pot.
+        pub fn pool_totals() -> PoolTotals<BalanceOf<T>> {
+            PoolTotals {
+                total_pooled: TotalPooled::<T>::get(),
+                staked_supply: Self::staked_supply(),
+                total_shares: TotalShares::<T>::get(),
+                pot: T::MainToken::free_balance(&Self::account_id()),
+                owed_rewards: OwedRewards::<T>::get(),
+            }
+        }
     }
 }
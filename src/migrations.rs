@@ -0,0 +1,444 @@
+//! Storage and custody migrations of the pallet.
+//!
+//! The storage migrations are versioned: `v1::MigrateToV1` runs from the `on_runtime_upgrade`
+//! hook of the pallet while the on-chain `StorageVersion` is behind, and moves the MainToken of
+//! version 0 into the custody of `Config::Custody` itself. The other custody migrations work on
+//! the storage of version 1 and must be added to the runtime upgrade that changes
+//! `Config::Custody`: the runtime runs them before the hooks of the pallets, so they can't be
+//! combined with a storage migration.
+
+use crate::{BalanceOf, Config, CustodyMode, Event, Ledger, Pallet, Percentage, Unbonding};
+use core::marker::PhantomData;
+use frame_support::sp_runtime::traits::{Saturating, Zero};
+use frame_support::sp_runtime::Perbill;
+use frame_support::storage::{unhashed, with_storage_layer, StorageValue};
+#[cfg(feature = "try-runtime")]
+use frame_support::traits::OnRuntimeUpgradeHelpersExt;
+use frame_support::traits::{
+    BalanceStatus, Get, NamedReservableCurrency, OnRuntimeUpgrade, ReservableCurrency,
+};
+use frame_support::weights::Weight;
+#[cfg(feature = "try-runtime")]
+use frame_support::{ensure, traits::Currency};
+
+/// Call `f` with the MainToken held in custody for each account: the principal of its ledger
+/// and its pending unbonding requests, then the reserve backing the rewards owed by the pot.
+/// Returns the number of storage entries visited.
+fn for_each_custody<T: Config>(mut f: impl FnMut(&T::AccountId, BalanceOf<T>)) -> u64 {
+    let mut entries: u64 = 0;
+
+    for (who, ledger) in Ledger::<T>::iter() {
+        entries += 1;
+        f(&who, ledger.principal);
+    }
+
+    for (who, chunks) in Unbonding::<T>::iter() {
+        entries += 1;
+        let pending = chunks
+            .iter()
+            .fold(Zero::zero(), |acc: BalanceOf<T>, chunk| {
+                acc.saturating_add(chunk.value)
+            });
+        f(&who, pending);
+    }
+
+    let pot = Pallet::<T>::account_id();
+    entries += 1;
+    f(
+        &pot,
+        T::MainToken::reserved_balance_named(&Pallet::<T>::reserve_id(), &pot),
+    );
+
+    entries
+}
+
+/// Moves the MainToken reserved on the stakers by `CustodyMode::Reserve` into the pool account.
+///
+/// Run it in the same runtime upgrade that switches `Config::Custody` to `CustodyMode::PoolAccount`:
+/// both the principal of every ledger and the pending unbonding requests are moved.
+/// The pool account must already exist, i.e. hold at least the existential deposit.
+pub struct MigrateToPoolAccount<T>(PhantomData<T>);
+
+impl<T: Config> OnRuntimeUpgrade for MigrateToPoolAccount<T> {
+    fn on_runtime_upgrade() -> Weight {
+        if T::Custody::get() != CustodyMode::PoolAccount {
+            return 0;
+        }
+
+        let pool_account = Pallet::<T>::pool_account();
+        let reserve_id = Pallet::<T>::reserve_id();
+        let entries = for_each_custody::<T>(|who, amount| {
+            // Whatever can't be repatriated stays reserved on the staker.
+            let _ = T::MainToken::repatriate_reserved_named(
+                &reserve_id,
+                who,
+                &pool_account,
+                amount,
+                BalanceStatus::Free,
+            );
+        });
+
+        // Every entry reads and writes the staker and the pool account.
+        T::DbWeight::get().reads_writes(entries.saturating_mul(3), entries.saturating_mul(2))
+    }
+
+    #[cfg(feature = "try-runtime")]
+    fn pre_upgrade() -> Result<(), &'static str> {
+        let mut custody: BalanceOf<T> = Zero::zero();
+        for_each_custody::<T>(|_, amount| custody = custody.saturating_add(amount));
+        let pool = T::MainToken::free_balance(&Pallet::<T>::pool_account());
+        Self::set_temp_storage((custody, pool), "custody");
+        Ok(())
+    }
+
+    #[cfg(feature = "try-runtime")]
+    fn post_upgrade() -> Result<(), &'static str> {
+        if T::Custody::get() != CustodyMode::PoolAccount {
+            return Ok(());
+        }
+        let (custody, pool): (BalanceOf<T>, BalanceOf<T>) =
+            Self::get_temp_storage("custody").ok_or("the custody was not recorded")?;
+        ensure!(
+            T::MainToken::free_balance(&Pallet::<T>::pool_account())
+                == pool.saturating_add(custody),
+            "the pool account doesn't hold all the staked MainToken"
+        );
+        Ok(())
+    }
+}
+
+/// Move `amount` of the anonymous reserve of `who` under `Pallet::reserve_id`, returning what
+/// was moved. Naming a reserve fails once the account has `MaxReserves` named reserves or under a
+/// lock covering reserves, e.g. vesting: the reserve is then left anonymous, nothing is moved and
+/// `Event::ReserveNotConverted` reports the account.
+fn name_reserve<T: Config>(who: &T::AccountId, amount: BalanceOf<T>) -> BalanceOf<T> {
+    let named = with_storage_layer(|| {
+        let moved = amount.saturating_sub(T::MainToken::unreserve(who, amount));
+        T::MainToken::reserve_named(&Pallet::<T>::reserve_id(), who, moved).map(|()| moved)
+    });
+    named.unwrap_or_else(|error| {
+        Pallet::<T>::deposit_event(Event::ReserveNotConverted(who.clone(), amount, error));
+        Zero::zero()
+    })
+}
+
+/// Converts the anonymous reserves created by `CustodyMode::Reserve` before named reserves
+/// were introduced into reserves named after `Pallet::reserve_id`.
+///
+/// Only the MainToken the pool accounts for is converted, the other reserves of the staker
+/// are left untouched and the total reserved balance of each account does not change. A reserve
+/// that can't be named stays anonymous and emits `Event::ReserveNotConverted`, so the account
+/// can be fixed by hand.
+pub struct MigrateToNamedReserve<T>(PhantomData<T>);
+
+impl<T: Config> OnRuntimeUpgrade for MigrateToNamedReserve<T> {
+    fn on_runtime_upgrade() -> Weight {
+        if T::Custody::get() != CustodyMode::Reserve {
+            return 0;
+        }
+
+        let reserve_id = Pallet::<T>::reserve_id();
+        let entries = for_each_custody::<T>(|who, amount| {
+            let anonymous = T::MainToken::reserved_balance(who)
+                .saturating_sub(T::MainToken::reserved_balance_named(&reserve_id, who));
+            name_reserve::<T>(who, amount.min(anonymous));
+        });
+
+        T::DbWeight::get().reads_writes(entries.saturating_mul(2), entries.saturating_mul(2))
+    }
+
+    #[cfg(feature = "try-runtime")]
+    fn pre_upgrade() -> Result<(), &'static str> {
+        let mut reserved: BalanceOf<T> = Zero::zero();
+        let entries = for_each_custody::<T>(|who, _| {
+            reserved = reserved.saturating_add(T::MainToken::reserved_balance(who))
+        });
+        Self::set_temp_storage((entries, reserved), "reserves");
+        Ok(())
+    }
+
+    #[cfg(feature = "try-runtime")]
+    fn post_upgrade() -> Result<(), &'static str> {
+        let (entries, reserved): (u64, BalanceOf<T>) =
+            Self::get_temp_storage("reserves").ok_or("the reserves were not recorded")?;
+        let mut after: BalanceOf<T> = Zero::zero();
+        let after_entries = for_each_custody::<T>(|who, _| {
+            after = after.saturating_add(T::MainToken::reserved_balance(who))
+        });
+        ensure!(after_entries == entries, "the number of stakers changed");
+        ensure!(after == reserved, "the total reserved balance changed");
+        Ok(())
+    }
+}
+
+/// Converts `Percentage` from the whole percent stored as a `u8` into a `Perbill`.
+///
+/// Only a value still encoded as a single byte is converted, so running it again is a no-op.
+pub struct MigrateToPerbillPercentage<T>(PhantomData<T>);
+
+impl<T: Config> OnRuntimeUpgrade for MigrateToPerbillPercentage<T> {
+    fn on_runtime_upgrade() -> Weight {
+        let key = Percentage::<T>::hashed_key();
+        let is_u8 = unhashed::get_raw(&key).map_or(false, |raw| raw.len() == 1);
+        if !is_u8 {
+            return T::DbWeight::get().reads(1);
+        }
+
+        // The old value can't be above 100, but clamp it anyway.
+        let _ = Percentage::<T>::translate(|old: Option<u8>| {
+            old.map(|percent| Perbill::from_percent(percent.min(100).into()))
+        });
+
+        T::DbWeight::get().reads_writes(1, 1)
+    }
+
+    #[cfg(feature = "try-runtime")]
+    fn pre_upgrade() -> Result<(), &'static str> {
+        let raw = unhashed::get_raw(&Percentage::<T>::hashed_key());
+        let old = raw.filter(|raw| raw.len() == 1).map(|raw| raw[0]);
+        Self::set_temp_storage(old, "percentage");
+        Ok(())
+    }
+
+    #[cfg(feature = "try-runtime")]
+    fn post_upgrade() -> Result<(), &'static str> {
+        let old: Option<u8> =
+            Self::get_temp_storage("percentage").ok_or("the percentage was not recorded")?;
+        if let Some(percent) = old {
+            ensure!(
+                Percentage::<T>::get() == Perbill::from_percent(percent.min(100).into()),
+                "the percentage was not converted"
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Migrations from version 0, the storage of the pallet before it declared a `StorageVersion`.
+pub mod v1 {
+    use super::*;
+    use crate::{RewardDebt, RewardPerShare, StakingLedger, TotalMinted, TotalPooled, TotalShares};
+    use frame_support::sp_runtime::helpers_128bit::multiply_by_rational;
+    use frame_support::sp_runtime::traits::SaturatedConversion;
+    use frame_support::sp_runtime::{FixedPointNumber, FixedU128};
+    use frame_support::storage::migration::storage_key_iter;
+    use frame_support::traits::tokens::{ExistenceRequirement, WithdrawReasons};
+    use frame_support::traits::{Currency, GetStorageVersion, PalletInfoAccess, StorageVersion};
+    use frame_support::Blake2_128Concat;
+
+    /// The name of the map of version 0 holding the block of the last stake of each account.
+    const STAKED_TIMES: &[u8] = b"StakedTimes";
+
+    /// Migrates the storage from version 0 to version 1:
+    /// + every `StakedTimes` entry becomes a `Ledger` with a single deposit chunk made at the
+    ///   block of the last stake, holding the StakedToken of the account and backed by the
+    ///   MainToken its stakes reserved, which moves from the anonymous reserve into the custody
+    ///   of `Config::Custody`;
+    /// + `Percentage` is converted into a `Perbill` by `MigrateToPerbillPercentage`;
+    /// + the StakedToken of the pot, which was never backed by MainToken, is burned. The pot now
+    ///   pays the rewards with MainToken, so it must be funded again with `fund_pot`;
+    /// + the remaining StakedToken issuance becomes `TotalMinted`.
+    ///
+    /// A reserve that can't be named in `CustodyMode::Reserve` stays anonymous and out of the
+    /// ledger, see `Event::ReserveNotConverted`. The StakedToken of accounts without a
+    /// `StakedTimes` entry, e.g. received with `transfer`, stays untracked. It only runs while the on-chain storage version is 0, so running it
+    /// again is a no-op.
+    pub struct MigrateToV1<T>(PhantomData<T>);
+
+    /// The whole percent of bonus StakedToken minted by `stake` in version 0, 1 by default.
+    fn old_percentage<T: Config>() -> u8 {
+        unhashed::get_raw(&Percentage::<T>::hashed_key())
+            .filter(|raw| raw.len() == 1)
+            .map_or(1, |raw| raw[0])
+            .min(100)
+    }
+
+    /// The number of reward distributions of version 0 since the block `since`: its
+    /// `on_finalize` paid every staker at each block multiple of 5, up to the upgrade.
+    fn reward_periods<T: Config>(since: T::BlockNumber) -> usize {
+        let since: u64 = since.saturated_into();
+        let now: u64 = frame_system::Pallet::<T>::block_number().saturated_into();
+        // One past the last multiple of 5 up to `now`, minus the first one from `since`.
+        (now / 5 + 1)
+            .saturating_sub(since.saturating_add(4) / 5)
+            .saturated_into()
+    }
+
+    /// The StakedToken and the MainToken backing the position of `who` in version 0, where
+    /// staking `amount` reserved it and minted `amount` plus `percent`% of it, and every
+    /// distribution paid `percent`% of the StakedToken of the staker out of the unbacked pot.
+    ///
+    /// Other pallets can reserve on the staker too, so the principal must never exceed what the
+    /// stakes reserved: the StakedToken is discounted by the bonus and by every distribution
+    /// since the last stake, assuming the percentage in effect at the upgrade, and rounded down.
+    /// It isn't capped at the reserve of the staker: `take_custody` moves what is reserved and
+    /// leaves the rest of the principal unbacked.
+    fn position<T: Config>(
+        who: &T::AccountId,
+        since: T::BlockNumber,
+        percent: u8,
+    ) -> (BalanceOf<T>, BalanceOf<T>) {
+        let minted = T::StakedToken::free_balance(who);
+        let growth = FixedU128::saturating_from_rational(100 + u32::from(percent), 100u32)
+            .saturating_pow(reward_periods::<T>(since).saturating_add(1));
+        let principal =
+            multiply_by_rational(minted.saturated_into(), FixedU128::DIV, growth.into_inner())
+                .map_or_else(|_| Zero::zero(), |principal| principal.saturated_into());
+        (minted, principal)
+    }
+
+    /// Move `principal` MainToken from the anonymous reserve of `who` into the custody of the
+    /// pool, returning what was moved.
+    fn take_custody<T: Config>(who: &T::AccountId, principal: BalanceOf<T>) -> BalanceOf<T> {
+        match T::Custody::get() {
+            CustodyMode::Reserve => name_reserve::<T>(who, principal),
+            CustodyMode::PoolAccount => {
+                let missing = T::MainToken::repatriate_reserved(
+                    who,
+                    &Pallet::<T>::pool_account(),
+                    principal,
+                    BalanceStatus::Free,
+                )
+                .unwrap_or(principal);
+                principal.saturating_sub(missing)
+            }
+        }
+    }
+
+    impl<T: Config> OnRuntimeUpgrade for MigrateToV1<T> {
+        fn on_runtime_upgrade() -> Weight {
+            if Pallet::<T>::on_chain_storage_version() != 0 {
+                return T::DbWeight::get().reads(1);
+            }
+
+            // Read before `Percentage` is converted into a `Perbill`.
+            let percent = old_percentage::<T>();
+            let mut weight = MigrateToPerbillPercentage::<T>::on_runtime_upgrade();
+
+            let pallet = <Pallet<T> as PalletInfoAccess>::name().as_bytes();
+            let reward_per_share = RewardPerShare::<T>::get();
+            let mut entries: u64 = 0;
+            for (who, since) in storage_key_iter::<T::AccountId, T::BlockNumber, Blake2_128Concat>(
+                pallet,
+                STAKED_TIMES,
+            )
+            .drain()
+            {
+                entries += 1;
+                let (minted, principal) = position::<T>(&who, since, percent);
+                if minted.is_zero() {
+                    continue;
+                }
+                let principal = take_custody::<T>(&who, principal);
+
+                let mut ledger = StakingLedger::<T>::default();
+                // An empty ledger always has room for a chunk.
+                let _ = ledger.deposit(minted, principal, since, since);
+                ledger.shares = minted;
+                ledger.last_stake = Some(since);
+                Ledger::<T>::insert(&who, ledger);
+                RewardDebt::<T>::insert(&who, reward_per_share.saturating_mul_int(minted));
+                TotalShares::<T>::mutate(|shares| *shares = shares.saturating_add(minted));
+                TotalPooled::<T>::mutate(|pooled| *pooled = pooled.saturating_add(principal));
+            }
+
+            // Dropping the returned imbalance burns the StakedToken of the pot.
+            let pot_address = Pallet::<T>::account_id();
+            let pot = T::StakedToken::free_balance(&pot_address);
+            if !pot.is_zero() {
+                let _ = T::StakedToken::withdraw(
+                    &pot_address,
+                    pot,
+                    WithdrawReasons::all(),
+                    ExistenceRequirement::AllowDeath,
+                );
+            }
+            // The StakedToken left from version 0 counts as minted by the pool.
+            TotalMinted::<T>::put(T::StakedToken::total_issuance());
+
+            StorageVersion::new(1).put::<Pallet<T>>();
+
+            // Every entry reads the old entry, both balances of the staker and the custody, and
+            // writes them with the ledger, the reward debt and the totals. The pot and the
+            // issuance are read and written once.
+            weight = weight.saturating_add(T::DbWeight::get().reads_writes(
+                entries.saturating_mul(5).saturating_add(4),
+                entries.saturating_mul(7).saturating_add(3),
+            ));
+            weight
+        }
+
+        #[cfg(feature = "try-runtime")]
+        fn pre_upgrade() -> Result<(), &'static str> {
+            if Pallet::<T>::on_chain_storage_version() != 0 {
+                return Ok(());
+            }
+            MigrateToPerbillPercentage::<T>::pre_upgrade()?;
+
+            let percent = old_percentage::<T>();
+            let pallet = <Pallet<T> as PalletInfoAccess>::name().as_bytes();
+            let mut stakers: u32 = 0;
+            let mut shares: BalanceOf<T> = TotalShares::<T>::get();
+            let mut pooled: BalanceOf<T> = TotalPooled::<T>::get();
+            for (who, since) in storage_key_iter::<T::AccountId, T::BlockNumber, Blake2_128Concat>(
+                pallet,
+                STAKED_TIMES,
+            ) {
+                let (minted, principal) = position::<T>(&who, since, percent);
+                if !minted.is_zero() {
+                    stakers += 1;
+                    shares = shares.saturating_add(minted);
+                    // `take_custody` moves the principal the staker still has reserved.
+                    pooled =
+                        pooled.saturating_add(principal.min(T::MainToken::reserved_balance(&who)));
+                }
+            }
+            let ledgers = Ledger::<T>::iter().count() as u32;
+            Self::set_temp_storage((ledgers.saturating_add(stakers), shares, pooled), "v1");
+            Ok(())
+        }
+
+        #[cfg(feature = "try-runtime")]
+        fn post_upgrade() -> Result<(), &'static str> {
+            ensure!(
+                Pallet::<T>::on_chain_storage_version() == 1,
+                "the storage version was not updated"
+            );
+            // Nothing was recorded if the migration already ran.
+            let expected: Option<(u32, BalanceOf<T>, BalanceOf<T>)> = Self::get_temp_storage("v1");
+            let (ledgers, shares, pooled) = match expected {
+                Some(expected) => expected,
+                None => return Ok(()),
+            };
+            MigrateToPerbillPercentage::<T>::post_upgrade()?;
+
+            let pallet = <Pallet<T> as PalletInfoAccess>::name().as_bytes();
+            ensure!(
+                storage_key_iter::<T::AccountId, T::BlockNumber, Blake2_128Concat>(
+                    pallet,
+                    STAKED_TIMES
+                )
+                .next()
+                .is_none(),
+                "`StakedTimes` was not removed"
+            );
+            ensure!(
+                Ledger::<T>::iter().count() as u32 == ledgers,
+                "the number of ledgers doesn't match the stakers"
+            );
+            ensure!(
+                TotalShares::<T>::get() == shares,
+                "`TotalShares` doesn't match the StakedToken of the stakers"
+            );
+            ensure!(
+                TotalPooled::<T>::get() == pooled,
+                "`TotalPooled` doesn't match the MainToken taken into custody"
+            );
+            ensure!(
+                T::StakedToken::free_balance(&Pallet::<T>::account_id()).is_zero(),
+                "the StakedToken of the pot was not burned"
+            );
+            Ok(())
+        }
+    }
+}
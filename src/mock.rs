@@ -1,24 +1,26 @@
 use crate as simple_pool;
+use crate::CustodyMode;
 use frame_support::instances::{Instance1, Instance2};
 use frame_support::traits::EqualPrivilegeOnly;
 use frame_support::traits::SortedMembers;
 use frame_support::traits::StorageMapShim;
-use frame_support::traits::{ConstU128, ConstU16, ConstU32, ConstU64};
+use frame_support::traits::{ConstU16, ConstU32, ConstU64};
 use frame_support::PalletId;
 use frame_support::{ord_parameter_types, parameter_types};
 use frame_system::EnsureRoot;
 use frame_system::EnsureSignedBy;
 
 use sp_core::H256;
-use sp_runtime::BuildStorage;
 use sp_runtime::{
     testing::Header,
     traits::{BlakeTwo256, IdentityLookup},
 };
+use sp_runtime::{BuildStorage, Perbill};
 
 type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
 type Block = frame_system::mocking::MockBlock<Test>;
-type Balance = u128;
+// The pallet runs with `u64` balances, the narrowest `Config::Balance` it supports.
+type Balance = u64;
 
 // Configure a mock runtime to test the pallet.
 frame_support::construct_runtime!(
@@ -28,12 +30,13 @@ frame_support::construct_runtime!(
         UncheckedExtrinsic = UncheckedExtrinsic,
     {
         System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
-        TemplateModule: simple_pool::{Pallet, Call, Storage, Event<T>},
         Scheduler: pallet_scheduler::{Pallet, Call, Storage, Event<T>},
         // Democracy: pallet_democracy exclude_parts { Call },
         Democracy: pallet_democracy::{Pallet, Call, Storage, Event<T>},
         Balances: pallet_balances::<Instance1>::{Pallet, Call, Storage, Config<T>, Event<T>},
         StakedBalances: pallet_balances::<Instance2>::{Pallet, Call, Storage, Config<T>, Event<T>},
+        // Built after the balances, so its genesis can stake them.
+        TemplateModule: simple_pool::{Pallet, Call, Storage, Config<T>, Event<T>},
     }
 );
 
@@ -83,7 +86,7 @@ impl pallet_balances::Config<MainToken> for Test {
     type Balance = Balance;
     type DustRemoval = ();
     type Event = Event;
-    type ExistentialDeposit = ConstU128<256>;
+    type ExistentialDeposit = ConstU64<256>;
     type AccountStore = StorageMapShim<
         pallet_balances::pallet::Account<Test, Instance1>,
         frame_system::Provider<Test>,
@@ -101,7 +104,7 @@ impl pallet_balances::Config<StakedToken> for Test {
     type Balance = Balance;
     type DustRemoval = ();
     type Event = Event;
-    type ExistentialDeposit = ConstU128<0>;
+    type ExistentialDeposit = ConstU64<0>;
     type AccountStore = StorageMapShim<
         pallet_balances::pallet::Account<Test, Instance2>,
         frame_system::Provider<Test>,
@@ -147,7 +150,7 @@ impl pallet_democracy::Config for Test {
     type VotingPeriod = ConstU64<2>;
     type VoteLockingPeriod = ConstU64<3>;
     type FastTrackVotingPeriod = ConstU64<2>;
-    type MinimumDeposit = ConstU128<1>;
+    type MinimumDeposit = ConstU64<1>;
     type ExternalOrigin = EnsureSignedBy<Two, u64>;
     type ExternalMajorityOrigin = EnsureSignedBy<Three, u64>;
     type ExternalDefaultOrigin = EnsureSignedBy<One, u64>;
@@ -171,28 +174,72 @@ impl pallet_democracy::Config for Test {
 
 parameter_types! {
     pub const SimplePoolId: PalletId = PalletId(*b"simplpol");
+    pub static PoolCustody: CustodyMode = CustodyMode::PoolAccount;
+    pub static ParameterChangeDelay: u64 = 0;
+    pub static CheckInvariants: bool = false;
+    pub const MaxRewardRate: Perbill = Perbill::from_percent(50);
+    pub const MaxStepChange: Perbill = Perbill::from_percent(20);
 }
 
 impl simple_pool::Config for Test {
     type Event = Event;
+    type Balance = Balance;
     type MainToken = Balances;
     type StakedToken = StakedBalances;
     type PalletId = SimplePoolId;
+    type MaxUnbondingChunks = ConstU32<4>;
+    type MaxDepositChunks = ConstU32<4>;
+    type Custody = PoolCustody;
+    type AdminOrigin = EnsureRoot<u64>;
+    type EmergencyOrigin = EnsureSignedBy<Six, u64>;
+    type DefaultRewardPeriod = ConstU64<5>;
+    type MaxRewardPeriod = ConstU64<100>;
+    type MinBlockToUnlock = ConstU32<1>;
+    type MaxBlockToUnlock = ConstU32<100>;
+    type MaxRewardRate = MaxRewardRate;
+    type MaxStepChange = MaxStepChange;
+    type DefaultMinStake = ConstU64<0>;
+    type DefaultMaxStakePerAccount = ConstU64<{ u64::MAX }>;
+    type DefaultMaxTotalStaked = ConstU64<{ u64::MAX }>;
+    type ParameterChangeDelay = ParameterChangeDelay;
+    type MaxPendingChanges = ConstU32<2>;
+    type CheckInvariants = CheckInvariants;
+    type WeightInfo = ();
 }
 
 // Build genesis storage according to the mock runtime.
 pub fn new_test_ext() -> sp_io::TestExternalities {
+    // Tests fund the pot when they need it.
+    new_test_ext_with(simple_pool::GenesisConfig {
+        pot: 0,
+        ..Default::default()
+    })
+}
+
+// Build genesis storage with the given pallet configuration.
+pub fn new_test_ext_with(
+    template_module: simple_pool::GenesisConfig<Test>,
+) -> sp_io::TestExternalities {
     let mut t = frame_system::GenesisConfig::default()
         .build_storage::<Test>()
         .unwrap();
 
     GenesisConfig {
         balances: BalancesConfig {
-            balances: vec![(1, 512), (2, 512), (3, 512), (4, 512), (5, 512)],
+            balances: vec![
+                (1, 512),
+                (2, 512),
+                (3, 512),
+                (4, 512),
+                (5, 512),
+                // The pool account is endowed with the existential deposit.
+                (TemplateModule::pool_account(), 256),
+            ],
         },
         staked_balances: StakedBalancesConfig {
             balances: vec![(1, 0), (2, 0), (3, 0), (4, 0), (5, 0)],
         },
+        template_module,
         ..Default::default()
     }
     .assimilate_storage(&mut t)
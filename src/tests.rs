@@ -1,5 +1,18 @@
-use crate::{mock::*, Error};
-use frame_support::{assert_noop, assert_ok};
+use crate::migrations::{
+    v1::MigrateToV1, MigrateToNamedReserve, MigrateToPerbillPercentage, MigrateToPoolAccount,
+};
+use crate::{
+    mock::*, mul_div, mul_div_up, CustodyMode, DepositChunk, Error, Invariant, OwedRewards,
+    ParameterChange, PauseState, PoolTotals, Position, RewardDestination, TotalPooled, TotalShares,
+    UnbondingChunk,
+};
+use codec::Encode;
+use frame_support::sp_runtime::{DispatchError, FixedPointNumber, FixedU128, Perbill};
+use frame_support::traits::{
+    Currency, ExistenceRequirement, GetStorageVersion, Hooks, LockableCurrency,
+    NamedReservableCurrency, OnRuntimeUpgrade, ReservableCurrency, StorageVersion, WithdrawReasons,
+};
+use frame_support::{assert_noop, assert_ok, Blake2_128Concat, StorageHasher, StorageValue};
 
 #[test]
 fn stake_works() {
@@ -80,6 +93,13 @@ fn complex_transfer_works() {
         assert_ok!(TemplateModule::stake(Origin::signed(1), 42));
         assert_ok!(TemplateModule::transfer(Origin::signed(1), 2, 42));
         assert_ok!(TemplateModule::transfer(Origin::signed(2), 1, 2));
+        // The transferred chunks keep their cooldown.
+        assert_noop!(
+            TemplateModule::unstake(Origin::signed(2), 40),
+            Error::<Test>::TooFastUnstake
+        );
+        let block_number = System::block_number();
+        System::set_block_number(block_number + 1);
         assert_ok!(TemplateModule::unstake(Origin::signed(2), 40));
     });
 }
@@ -107,11 +127,58 @@ fn transfer_too_much() {
 }
 
 #[test]
-fn percentage_works() {
+fn stake_mints_at_exchange_rate() {
+    new_test_ext().execute_with(|| {
+        // An empty pool mints 1:1.
+        assert_ok!(TemplateModule::stake(Origin::signed(1), 100));
+        assert_eq!(StakedBalances::free_balance(&1), 100);
+        assert_eq!(TotalPooled::<Test>::get(), 100);
+        assert_noop!(
+            TemplateModule::transfer(Origin::signed(1), 2, 101),
+            Error::<Test>::NotEnoughStakedToken
+        );
+
+        // Unbacked StakedToken doubles the supply, so the rate halves.
+        let _ = StakedBalances::deposit_creating(&2, 100);
+        assert_eq!(
+            TemplateModule::exchange_rate(),
+            FixedU128::saturating_from_rational(1, 2)
+        );
+        assert_ok!(TemplateModule::stake(Origin::signed(3), 100));
+        assert_eq!(StakedBalances::free_balance(&3), 200);
+        assert_eq!(TotalPooled::<Test>::get(), 200);
+    });
+}
+
+#[test]
+fn unstake_redeems_at_exchange_rate() {
     new_test_ext().execute_with(|| {
-        // Ensure the expected error is thrown when you unstake more than you have.
         assert_ok!(TemplateModule::stake(Origin::signed(1), 100));
-        assert_ok!(TemplateModule::transfer(Origin::signed(1), 2, 101));
+        let _ = StakedBalances::deposit_creating(&2, 100);
+        let block_number = System::block_number();
+        System::set_block_number(block_number + 1);
+
+        // 100 LDOT out of 200 are a claim on half of the pool.
+        assert_ok!(TemplateModule::unstake(Origin::signed(2), 100));
+        assert_eq!(StakedBalances::free_balance(&2), 0);
+        assert_eq!(TotalPooled::<Test>::get(), 50);
+        assert_eq!(
+            TemplateModule::exchange_rate(),
+            FixedU128::saturating_from_rational(1, 2)
+        );
+    });
+}
+
+#[test]
+fn stake_worth_zero_shares_fails() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(TemplateModule::stake(Origin::signed(1), 1));
+        // 1 LDOT is now worth 10 DOT, staking 1 DOT would mint nothing.
+        TotalPooled::<Test>::put(10);
+        assert_noop!(
+            TemplateModule::stake(Origin::signed(2), 1),
+            Error::<Test>::ZeroAmount
+        );
     });
 }
 
@@ -130,16 +197,1768 @@ fn unstake_too_much_tokens() {
 fn change_percentage() {
     new_test_ext().execute_with(|| {
         // Ensure the expected error is thrown when you unstake more than you have.
+        assert_ok!(TemplateModule::change_percentage(
+            Origin::root(),
+            Perbill::from_percent(5)
+        ));
+        assert_eq!(TemplateModule::percentage(), Perbill::from_percent(5));
+        assert_noop!(
+            TemplateModule::change_percentage(Origin::root(), Perbill::from_parts(1_000_000_001)),
+            Error::<Test>::PercentageTooHigh
+        );
+    });
+}
+
+#[test]
+fn unstake_queues_unbonding_request() {
+    new_test_ext().execute_with(|| {
         assert_ok!(TemplateModule::stake(Origin::signed(1), 100));
-        assert_ok!(TemplateModule::transfer(Origin::signed(1), 2, 101));
+        System::set_block_number(2);
+        assert_ok!(TemplateModule::unstake(Origin::signed(1), 60));
+
+        // The `MainToken` tokens stay in the pool until the request is withdrawn.
+        assert_eq!(Balances::free_balance(&1), 412);
+        assert_eq!(Balances::free_balance(&TemplateModule::pool_account()), 356);
+        assert_eq!(
+            TemplateModule::unbonding(1).into_inner(),
+            vec![UnbondingChunk {
+                value: 60,
+                unlock_at: 3
+            }]
+        );
         assert_noop!(
-            TemplateModule::transfer(Origin::signed(1), 2, 102),
-            Error::<Test>::NotEnoughStakedToken
+            TemplateModule::withdraw_unbonded(Origin::signed(1)),
+            Error::<Test>::NothingToWithdraw
         );
-        assert_ok!(TemplateModule::change_percentage(Origin::root(), 5));
-        let block_number = System::block_number();
-        System::set_block_number(block_number + 1);
+
+        System::set_block_number(3);
+        assert_ok!(TemplateModule::withdraw_unbonded(Origin::signed(1)));
+        assert_eq!(Balances::free_balance(&1), 472);
+        assert_eq!(Balances::free_balance(&TemplateModule::pool_account()), 296);
+        assert!(TemplateModule::unbonding(1).is_empty());
+    });
+}
+
+#[test]
+fn withdraw_unbonded_keeps_pending_requests() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(TemplateModule::stake(Origin::signed(1), 100));
+        System::set_block_number(2);
+        assert_ok!(TemplateModule::unstake(Origin::signed(1), 10));
+        System::set_block_number(3);
+        assert_ok!(TemplateModule::unstake(Origin::signed(1), 20));
+
+        assert_ok!(TemplateModule::withdraw_unbonded(Origin::signed(1)));
+        assert_eq!(Balances::free_balance(&1), 422);
+        assert_eq!(TemplateModule::unbonding(1).len(), 1);
+
+        System::set_block_number(4);
+        assert_ok!(TemplateModule::withdraw_unbonded(Origin::signed(1)));
+        assert_eq!(Balances::free_balance(&1), 442);
+    });
+}
+
+#[test]
+fn too_many_unbonding_requests() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(TemplateModule::stake(Origin::signed(1), 100));
+        System::set_block_number(2);
+        for _ in 0..4 {
+            assert_ok!(TemplateModule::unstake(Origin::signed(1), 1));
+        }
+        assert_noop!(
+            TemplateModule::unstake(Origin::signed(1), 1),
+            Error::<Test>::TooManyUnbondingChunks
+        );
+    });
+}
+
+#[test]
+fn stake_tracks_deposit_chunks() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(TemplateModule::stake(Origin::signed(1), 100));
+        assert_noop!(
+            TemplateModule::stake(Origin::signed(1), 50),
+            Error::<Test>::TooFastStake
+        );
+        System::set_block_number(2);
+        assert_ok!(TemplateModule::stake(Origin::signed(1), 50));
+
+        let ledger = TemplateModule::ledger(1).unwrap();
+        assert_eq!(ledger.principal, 150);
+        assert_eq!(ledger.minted, 150);
+        assert_eq!(
+            ledger.chunks.into_inner(),
+            vec![
+                DepositChunk {
+                    value: 100,
+                    since: 1
+                },
+                DepositChunk {
+                    value: 50,
+                    since: 2
+                }
+            ]
+        );
+    });
+}
+
+#[test]
+fn partial_unstake_keeps_locked_chunks() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(TemplateModule::stake(Origin::signed(1), 100));
+        System::set_block_number(2);
+        assert_ok!(TemplateModule::stake(Origin::signed(1), 50));
+
+        // Only the first chunk completed its cooldown.
+        assert_noop!(
+            TemplateModule::unstake(Origin::signed(1), 101),
+            Error::<Test>::TooFastUnstake
+        );
+        assert_ok!(TemplateModule::unstake(Origin::signed(1), 100));
+
+        let ledger = TemplateModule::ledger(1).unwrap();
+        assert_eq!(ledger.principal, 50);
+        assert_eq!(ledger.minted, 50);
+        assert_eq!(
+            ledger.chunks.into_inner(),
+            vec![DepositChunk {
+                value: 50,
+                since: 2
+            }]
+        );
+
+        System::set_block_number(3);
+        assert_ok!(TemplateModule::unstake(Origin::signed(1), 50));
+        assert_eq!(TemplateModule::ledger(1), None);
+    });
+}
+
+#[test]
+fn transfer_moves_ledger_chunks() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(TemplateModule::stake(Origin::signed(1), 100));
+        assert_ok!(TemplateModule::transfer(Origin::signed(1), 2, 30));
+
+        let sender = TemplateModule::ledger(1).unwrap();
+        assert_eq!((sender.principal, sender.minted), (70, 70));
+        let receiver = TemplateModule::ledger(2).unwrap();
+        assert_eq!((receiver.principal, receiver.minted), (30, 30));
+        assert_eq!(
+            receiver.chunks.into_inner(),
+            vec![DepositChunk {
+                value: 30,
+                since: 1
+            }]
+        );
+    });
+}
+
+#[test]
+fn received_chunks_keep_the_ledger_ordered() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(TemplateModule::stake(Origin::signed(3), 100));
+        System::set_block_number(3);
+        assert_ok!(TemplateModule::stake(Origin::signed(2), 100));
+        assert_ok!(TemplateModule::transfer(Origin::signed(3), 2, 10));
+
+        assert_eq!(
+            TemplateModule::ledger(2).unwrap().chunks.into_inner(),
+            vec![
+                DepositChunk {
+                    value: 10,
+                    since: 1
+                },
+                DepositChunk {
+                    value: 100,
+                    since: 3
+                }
+            ]
+        );
+    });
+}
+
+#[test]
+fn dust_transfers_cant_fill_the_ledger() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(TemplateModule::change_block_time(Origin::root(), 10));
+        crate::MinStake::<Test>::put(10);
+        assert_ok!(TemplateModule::stake(Origin::signed(1), 100));
+        assert_ok!(TemplateModule::stake(Origin::signed(2), 100));
+        // Dust from later deposits can't add chunks to the ledger of account 2.
+        for who in 3..=5 {
+            System::set_block_number(who);
+            assert_ok!(TemplateModule::stake(Origin::signed(who), 100));
+            assert_noop!(
+                TemplateModule::transfer(Origin::signed(who), 2, 9),
+                Error::<Test>::TransferBelowMinimumStake
+            );
+        }
+
+        // Dust joins a chunk made at or after it, which keeps its cooldown.
+        assert_ok!(TemplateModule::transfer(Origin::signed(1), 2, 9));
+        // A transfer worth `MinStake` still gets its own chunk.
+        assert_ok!(TemplateModule::transfer(Origin::signed(3), 2, 10));
+        assert_eq!(
+            TemplateModule::ledger(2).unwrap().chunks.into_inner(),
+            vec![
+                DepositChunk {
+                    value: 109,
+                    since: 1
+                },
+                DepositChunk {
+                    value: 10,
+                    since: 3
+                }
+            ]
+        );
+
+        // An empty ledger takes the dust in a chunk of its own.
+        assert_ok!(TemplateModule::transfer(Origin::signed(4), 6, 9));
+        assert_eq!(
+            TemplateModule::ledger(6).unwrap().chunks.into_inner(),
+            vec![DepositChunk { value: 9, since: 4 }]
+        );
+    });
+}
+
+#[test]
+fn dust_transfers_do_not_restart_the_cooldown() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(TemplateModule::change_block_time(Origin::root(), 10));
+        assert_ok!(TemplateModule::stake(Origin::signed(2), 100));
+        System::set_block_number(2);
+        assert_ok!(TemplateModule::stake(Origin::signed(1), 100));
+        // Dust from later deposits fills the ledger of account 2.
+        for who in 3..=5 {
+            System::set_block_number(who);
+            assert_ok!(TemplateModule::stake(Origin::signed(who), 100));
+            assert_ok!(TemplateModule::transfer(Origin::signed(who), 2, 1));
+        }
+
+        // An older chunk joins the next newer one, which keeps its cooldown.
+        System::set_block_number(6);
+        assert_ok!(TemplateModule::transfer(Origin::signed(1), 2, 1));
+        // A newer one would restart a cooldown, so it is rejected.
+        let _ = Balances::make_free_balance_be(&6, 512);
+        assert_ok!(TemplateModule::stake(Origin::signed(6), 100));
+        assert_noop!(
+            TemplateModule::transfer(Origin::signed(6), 2, 1),
+            Error::<Test>::TooManyDepositChunks
+        );
+        assert_eq!(
+            TemplateModule::ledger(2).unwrap().chunks.into_inner(),
+            vec![
+                DepositChunk {
+                    value: 100,
+                    since: 1
+                },
+                DepositChunk { value: 2, since: 3 },
+                DepositChunk { value: 1, since: 4 },
+                DepositChunk { value: 1, since: 5 }
+            ]
+        );
+
+        // Only the own stake of account 2 counts for its cooldowns.
+        System::set_block_number(10);
+        assert_noop!(
+            TemplateModule::stake(Origin::signed(2), 100),
+            Error::<Test>::TooFastStake
+        );
+        System::set_block_number(11);
+        assert_ok!(TemplateModule::unstake(Origin::signed(2), 100));
+        assert_ok!(TemplateModule::stake(Origin::signed(2), 100));
+    });
+}
+
+#[test]
+fn rewards_accrue_per_share() {
+    new_test_ext().execute_with(|| {
+        let _ = Balances::deposit_creating(&TemplateModule::account_id(), 1_000);
+        assert_ok!(TemplateModule::stake(Origin::signed(1), 100));
+        assert_eq!(TemplateModule::total_shares(), 100);
+
+        // Only the accumulator is updated, nothing is paid yet.
+        TemplateModule::on_finalize(5);
+        assert_eq!(
+            TemplateModule::reward_per_share(),
+            FixedU128::saturating_from_rational(1, 100)
+        );
+        assert_eq!(StakedBalances::free_balance(&1), 100);
+        assert_eq!(TemplateModule::pending_rewards(&1), 1);
+
+        assert_ok!(TemplateModule::claim_rewards(Origin::signed(1)));
+        assert_eq!(StakedBalances::free_balance(&1), 101);
+        assert_eq!(TemplateModule::pending_rewards(&1), 0);
+        assert_eq!(TemplateModule::ledger(1).unwrap().minted, 101);
+        assert_eq!(TemplateModule::total_shares(), 101);
+    });
+}
+
+#[test]
+fn late_stakers_do_not_earn_past_rewards() {
+    new_test_ext().execute_with(|| {
+        let _ = Balances::deposit_creating(&TemplateModule::account_id(), 1_000);
+        assert_ok!(TemplateModule::stake(Origin::signed(1), 100));
+        TemplateModule::on_finalize(5);
+
+        assert_ok!(TemplateModule::stake(Origin::signed(2), 100));
+        assert_eq!(TemplateModule::pending_rewards(&2), 0);
+        assert_noop!(
+            TemplateModule::claim_rewards(Origin::signed(2)),
+            Error::<Test>::NothingToClaim
+        );
+        assert_eq!(TemplateModule::pending_rewards(&1), 1);
+    });
+}
+
+#[test]
+fn stake_pays_the_rewards_before_minting() {
+    new_test_ext().execute_with(|| {
+        let _ = Balances::deposit_creating(&TemplateModule::account_id(), 1_000);
+        assert_ok!(TemplateModule::stake(Origin::signed(1), 100));
+        TemplateModule::on_finalize(5);
+        System::set_block_number(2);
+
+        // The reward is compounded before the new StakedToken is minted, at the same 1:1 rate.
+        assert_ok!(TemplateModule::stake(Origin::signed(1), 100));
+        assert_eq!(StakedBalances::free_balance(&1), 201);
+        assert_eq!(TemplateModule::ledger(1).unwrap().minted, 201);
+        assert_eq!(
+            TemplateModule::exchange_rate(),
+            FixedU128::saturating_from_integer(1)
+        );
+        assert_eq!(TemplateModule::pending_rewards(&1), 0);
+    });
+}
+
+#[test]
+fn rewards_are_settled_on_transfer() {
+    new_test_ext().execute_with(|| {
+        let _ = Balances::deposit_creating(&TemplateModule::account_id(), 1_000);
+        assert_ok!(TemplateModule::stake(Origin::signed(1), 100));
+        TemplateModule::on_finalize(5);
+
+        // The reward is paid before the shares leave the sender.
+        assert_ok!(TemplateModule::transfer(Origin::signed(1), 2, 50));
+        assert_eq!(StakedBalances::free_balance(&1), 51);
+        assert_eq!(StakedBalances::free_balance(&2), 50);
+        assert_eq!(TemplateModule::pending_rewards(&1), 0);
+        assert_eq!(TemplateModule::pending_rewards(&2), 0);
+        assert_eq!(TemplateModule::total_shares(), 101);
+    });
+}
+
+#[test]
+fn rewards_follow_the_staked_token_held() {
+    new_test_ext().execute_with(|| {
+        let _ = Balances::deposit_creating(&TemplateModule::account_id(), 1_000);
+        assert_ok!(TemplateModule::stake(Origin::signed(1), 200));
+        assert_ok!(TemplateModule::stake(Origin::signed(2), 200));
+        // Account 1 sells half of its StakedToken outside of the pallet.
+        assert_ok!(<StakedBalances as Currency<_>>::transfer(
+            &1,
+            &3,
+            100,
+            ExistenceRequirement::AllowDeath
+        ));
+        TemplateModule::on_finalize(5);
+
+        // Only the StakedToken still held earns, the rest of the reward stays in the pot.
+        assert_eq!(TemplateModule::pending_rewards(&1), 1);
+        assert_eq!(TemplateModule::pending_rewards(&2), 2);
+        assert_eq!(TemplateModule::pending_rewards(&3), 0);
+        assert_ok!(TemplateModule::claim_rewards(Origin::signed(1)));
+        assert_eq!(StakedBalances::free_balance(&1), 101);
+        assert_eq!(TemplateModule::ledger(1).unwrap().shares, 101);
+        assert_eq!(TemplateModule::total_shares(), 301);
+        assert_eq!(OwedRewards::<Test>::get(), 2);
+        assert_ok!(TemplateModule::do_try_state());
+    });
+}
+
+#[test]
+fn stake_fails_closed_when_reserve_fails() {
+    new_test_ext().execute_with(|| {
+        PoolCustody::set(CustodyMode::Reserve);
+        // The free balance is there, but it can't be reserved.
+        Balances::set_lock(*b"testlock", &1, 500, WithdrawReasons::all());
+        assert_noop!(
+            TemplateModule::stake(Origin::signed(1), 100),
+            Error::<Test>::ReserveFailed
+        );
+    });
+}
+
+#[test]
+fn rewards_are_backed_by_the_pot() {
+    for custody in [CustodyMode::PoolAccount, CustodyMode::Reserve] {
+        new_test_ext().execute_with(|| {
+            PoolCustody::set(custody);
+            let _ = Balances::deposit_creating(&TemplateModule::account_id(), 1_000);
+            assert_ok!(TemplateModule::stake(Origin::signed(1), 100));
+
+            // The MainToken backing the reward moves into the custody of the pool,
+            // so the exchange rate doesn't change.
+            TemplateModule::on_finalize(5);
+            assert_eq!(Balances::free_balance(&TemplateModule::account_id()), 999);
+            assert_eq!(TemplateModule::total_pooled(), 101);
+            assert_ok!(TemplateModule::claim_rewards(Origin::signed(1)));
+            assert_eq!(
+                TemplateModule::exchange_rate(),
+                FixedU128::saturating_from_integer(1)
+            );
+
+            // Sweeping the pot can't touch the pooled MainToken.
+            assert_ok!(TemplateModule::sweep_pot(Origin::root(), 2, 743));
+            System::set_block_number(2);
+            assert_ok!(TemplateModule::unstake(Origin::signed(1), 101));
+            System::set_block_number(3);
+            assert_ok!(TemplateModule::withdraw_unbonded(Origin::signed(1)));
+            assert_eq!(Balances::free_balance(&1), 513);
+            assert_ok!(TemplateModule::do_try_state());
+        });
+    }
+}
+
+#[test]
+fn rewards_stay_unclaimed_when_the_pot_cannot_pay() {
+    new_test_ext().execute_with(|| {
+        let _ = Balances::deposit_creating(&TemplateModule::account_id(), 1_000);
+        assert_ok!(TemplateModule::stake(Origin::signed(1), 100));
+        TemplateModule::on_finalize(5);
+
+        // The StakedToken owed by the pot is taken after the distribution.
+        let _ = StakedBalances::make_free_balance_be(&TemplateModule::account_id(), 0);
+        assert_noop!(
+            TemplateModule::claim_rewards(Origin::signed(1)),
+            Error::<Test>::PotInsufficient
+        );
+
+        // Other operations keep working, the reward stays unclaimed.
+        assert_ok!(TemplateModule::transfer(Origin::signed(1), 2, 10));
+        System::assert_has_event(Event::TemplateModule(crate::Event::RewardSkipped(
+            1,
+            1,
+            Error::<Test>::PotInsufficient.into(),
+        )));
+        assert_eq!(TemplateModule::ledger(1).unwrap().unclaimed, 1);
+        assert_eq!(TemplateModule::pending_rewards(&1), 1);
+
+        let _ = StakedBalances::deposit_creating(&TemplateModule::account_id(), 1);
+        assert_ok!(TemplateModule::claim_rewards(Origin::signed(1)));
+        assert_eq!(StakedBalances::free_balance(&1), 91);
+    });
+}
+
+#[test]
+fn pool_account_pays_any_holder() {
+    new_test_ext().execute_with(|| {
+        // An unrelated reserve, e.g. a democracy deposit.
+        assert_ok!(Balances::reserve(&2, 100));
+        assert_ok!(TemplateModule::stake(Origin::signed(1), 100));
+        assert_ok!(TemplateModule::transfer(Origin::signed(1), 2, 100));
+
+        System::set_block_number(2);
+        assert_ok!(TemplateModule::unstake(Origin::signed(2), 100));
+        System::set_block_number(3);
+        assert_ok!(TemplateModule::withdraw_unbonded(Origin::signed(2)));
+
+        assert_eq!(Balances::free_balance(&2), 512);
+        assert_eq!(Balances::reserved_balance(&2), 100);
+        assert_eq!(Balances::free_balance(&TemplateModule::pool_account()), 256);
+    });
+}
+
+#[test]
+fn reserve_custody_moves_backing_with_transfer() {
+    new_test_ext().execute_with(|| {
+        PoolCustody::set(CustodyMode::Reserve);
+        assert_ok!(Balances::reserve(&2, 50));
+        assert_ok!(TemplateModule::stake(Origin::signed(1), 100));
+        assert_eq!(Balances::reserved_balance(&1), 100);
+
+        assert_ok!(TemplateModule::transfer(Origin::signed(1), 2, 40));
+        assert_eq!(Balances::reserved_balance(&1), 60);
+        assert_eq!(Balances::reserved_balance(&2), 90);
+
+        System::set_block_number(2);
+        assert_ok!(TemplateModule::unstake(Origin::signed(2), 40));
+        System::set_block_number(3);
+        assert_ok!(TemplateModule::withdraw_unbonded(Origin::signed(2)));
+
+        // Only the backing received with the transfer is released.
+        assert_eq!(Balances::reserved_balance(&2), 50);
+        assert_eq!(Balances::free_balance(&2), 502);
+    });
+}
+
+#[test]
+fn reserve_custody_rejects_unbacked_staked_token() {
+    new_test_ext().execute_with(|| {
+        PoolCustody::set(CustodyMode::Reserve);
+        assert_ok!(Balances::reserve(&2, 50));
+        let _ = StakedBalances::deposit_creating(&2, 50);
+        assert_noop!(
+            TemplateModule::unstake(Origin::signed(2), 50),
+            Error::<Test>::UntrackedStakedToken
+        );
+
+        // The untracked StakedToken is never burned along with the tracked one.
+        assert_ok!(TemplateModule::stake(Origin::signed(2), 100));
+        System::set_block_number(2);
+        assert_noop!(
+            TemplateModule::unstake(Origin::signed(2), 120),
+            Error::<Test>::UntrackedStakedToken
+        );
+        assert_ok!(TemplateModule::unstake(Origin::signed(2), 100));
+        assert_eq!(StakedBalances::free_balance(&2), 50);
+        assert_eq!(TemplateModule::unbonding(2)[0].value, 100);
+    });
+}
+
+#[test]
+fn reserve_custody_compounds_the_rewards() {
+    new_test_ext().execute_with(|| {
+        PoolCustody::set(CustodyMode::Reserve);
+        let _ = Balances::deposit_creating(&TemplateModule::account_id(), 1_000);
+        assert_ok!(TemplateModule::stake(Origin::signed(1), 100));
+        for destination in [RewardDestination::Free, RewardDestination::Account(3)] {
+            assert_noop!(
+                TemplateModule::set_reward_destination(Origin::signed(1), destination),
+                Error::<Test>::InvalidRewardDestination
+            );
+        }
+
+        // The backing of the reward is reserved on the staker, with its principal.
+        TemplateModule::on_finalize(5);
+        assert_ok!(TemplateModule::claim_rewards(Origin::signed(1)));
+        let ledger = TemplateModule::ledger(1).unwrap();
+        assert_eq!((ledger.principal, ledger.minted), (101, 101));
+        assert_eq!(
+            Balances::reserved_balance_named(&TemplateModule::reserve_id(), &1),
+            101
+        );
+    });
+}
+
+#[test]
+fn migrate_to_pool_account_works() {
+    new_test_ext().execute_with(|| {
+        PoolCustody::set(CustodyMode::Reserve);
+        assert_ok!(TemplateModule::stake(Origin::signed(1), 100));
+        System::set_block_number(2);
+        assert_ok!(TemplateModule::unstake(Origin::signed(1), 30));
+        assert_eq!(Balances::reserved_balance(&1), 100);
+
+        PoolCustody::set(CustodyMode::PoolAccount);
+        MigrateToPoolAccount::<Test>::on_runtime_upgrade();
+        assert_eq!(Balances::reserved_balance(&1), 0);
+        assert_eq!(Balances::free_balance(&TemplateModule::pool_account()), 356);
+
+        // The pending request is now paid by the pool account.
+        System::set_block_number(3);
+        assert_ok!(TemplateModule::withdraw_unbonded(Origin::signed(1)));
+        assert_eq!(Balances::free_balance(&1), 442);
+    });
+}
+
+#[test]
+fn reserve_custody_uses_named_reserve() {
+    new_test_ext().execute_with(|| {
+        PoolCustody::set(CustodyMode::Reserve);
+        assert_ok!(Balances::reserve(&1, 50));
+        assert_ok!(TemplateModule::stake(Origin::signed(1), 100));
+        let reserve_id = TemplateModule::reserve_id();
+        assert_eq!(Balances::reserved_balance_named(&reserve_id, &1), 100);
+        assert_eq!(Balances::reserved_balance(&1), 150);
+    });
+}
+
+#[test]
+fn withdraw_never_releases_other_reserves() {
+    new_test_ext().execute_with(|| {
+        PoolCustody::set(CustodyMode::Reserve);
+        assert_ok!(Balances::reserve(&1, 50));
+        assert_ok!(TemplateModule::stake(Origin::signed(1), 100));
+        System::set_block_number(2);
+        assert_ok!(TemplateModule::unstake(Origin::signed(1), 100));
+
+        // Something else released the reserve of the pool.
+        Balances::unreserve_named(&TemplateModule::reserve_id(), &1, 100);
+        System::set_block_number(3);
+        assert_noop!(
+            TemplateModule::withdraw_unbonded(Origin::signed(1)),
+            Error::<Test>::UnreserveFailed
+        );
+        assert_eq!(Balances::reserved_balance(&1), 50);
+    });
+}
+
+#[test]
+fn migrate_to_named_reserve_works() {
+    new_test_ext().execute_with(|| {
+        PoolCustody::set(CustodyMode::Reserve);
+        assert_ok!(Balances::reserve(&1, 50));
+        assert_ok!(TemplateModule::stake(Origin::signed(1), 100));
+
+        // Turn the named reserve back into an anonymous one, as before named reserves.
+        let reserve_id = TemplateModule::reserve_id();
+        Balances::unreserve_named(&reserve_id, &1, 100);
+        assert_ok!(Balances::reserve(&1, 100));
+
+        MigrateToNamedReserve::<Test>::on_runtime_upgrade();
+        assert_eq!(Balances::reserved_balance_named(&reserve_id, &1), 100);
+        assert_eq!(Balances::reserved_balance(&1), 150);
+    });
+}
+
+#[test]
+fn migrate_to_named_reserve_reports_a_reserve_it_cant_name() {
+    new_test_ext().execute_with(|| {
+        PoolCustody::set(CustodyMode::Reserve);
+        assert_ok!(Balances::reserve(&1, 50));
         assert_ok!(TemplateModule::stake(Origin::signed(1), 100));
-        assert_ok!(TemplateModule::transfer(Origin::signed(1), 2, 105));
+        let reserve_id = TemplateModule::reserve_id();
+        Balances::unreserve_named(&reserve_id, &1, 100);
+        assert_ok!(Balances::reserve(&1, 100));
+
+        // A vesting-like lock forbids reserving the funds again once unreserved.
+        Balances::set_lock(*b"vesting ", &1, 1_000, WithdrawReasons::RESERVE);
+        MigrateToNamedReserve::<Test>::on_runtime_upgrade();
+        System::assert_has_event(Event::TemplateModule(crate::Event::ReserveNotConverted(
+            1,
+            100,
+            pallet_balances::Error::<Test, pallet_balances::Instance1>::LiquidityRestrictions
+                .into(),
+        )));
+        // The reserve stays anonymous instead of being released.
+        assert_eq!(Balances::reserved_balance_named(&reserve_id, &1), 0);
+        assert_eq!(Balances::reserved_balance(&1), 150);
+    });
+}
+
+#[test]
+fn change_reward_period() {
+    new_test_ext().execute_with(|| {
+        assert_eq!(TemplateModule::reward_period(), 5);
+        assert_noop!(
+            TemplateModule::change_reward_period(Origin::signed(1), 10),
+            DispatchError::BadOrigin
+        );
+        assert_noop!(
+            TemplateModule::change_reward_period(Origin::root(), 0),
+            Error::<Test>::RewardPeriodOutOfBounds
+        );
+        assert_noop!(
+            TemplateModule::change_reward_period(Origin::root(), 101),
+            Error::<Test>::RewardPeriodOutOfBounds
+        );
+
+        assert_ok!(TemplateModule::change_reward_period(Origin::root(), 10));
+        assert_eq!(TemplateModule::reward_period(), 10);
+        System::assert_has_event(Event::TemplateModule(crate::Event::RewardPeriodChanged(
+            5, 10,
+        )));
+    });
+}
+
+#[test]
+fn changing_reward_period_mid_cycle_keeps_the_schedule() {
+    new_test_ext().execute_with(|| {
+        let rewards = |n| FixedU128::saturating_from_rational(n, 100);
+        let _ = Balances::deposit_creating(&TemplateModule::account_id(), 1_000);
+        assert_ok!(TemplateModule::stake(Origin::signed(1), 100));
+        TemplateModule::on_finalize(5);
+        assert_eq!(TemplateModule::reward_per_share(), rewards(1));
+
+        // Lengthening the period delays the next distribution, without paying it twice.
+        assert_ok!(TemplateModule::change_reward_period(Origin::root(), 10));
+        TemplateModule::on_finalize(10);
+        assert_eq!(TemplateModule::reward_per_share(), rewards(1));
+        TemplateModule::on_finalize(15);
+        assert_eq!(TemplateModule::reward_per_share(), rewards(2));
+
+        // Shortening it below the elapsed blocks distributes at the next block, without skipping.
+        TemplateModule::on_finalize(18);
+        assert_ok!(TemplateModule::change_reward_period(Origin::root(), 2));
+        TemplateModule::on_finalize(19);
+        assert_eq!(TemplateModule::reward_per_share(), rewards(3));
+        assert_eq!(TemplateModule::last_reward_block(), 19);
+        TemplateModule::on_finalize(20);
+        assert_eq!(TemplateModule::reward_per_share(), rewards(3));
+    });
+}
+
+#[test]
+fn fund_pot_works() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(
+            TemplateModule::fund_pot(Origin::signed(1), 0),
+            Error::<Test>::ZeroAmount
+        );
+        assert_noop!(
+            TemplateModule::fund_pot(Origin::signed(2), 1_000),
+            Error::<Test>::NotEnoughMainToken
+        );
+
+        assert_ok!(TemplateModule::fund_pot(Origin::signed(1), 256));
+        assert_eq!(Balances::free_balance(&TemplateModule::account_id()), 256);
+        assert_eq!(Balances::free_balance(&1), 256);
+        System::assert_last_event(Event::TemplateModule(crate::Event::PotFunded(1, 256)));
+    });
+}
+
+#[test]
+fn sweep_pot_keeps_owed_rewards() {
+    new_test_ext().execute_with(|| {
+        let _ = Balances::deposit_creating(&TemplateModule::account_id(), 1_000);
+        assert_ok!(TemplateModule::stake(Origin::signed(1), 100));
+        // The MainToken backing the distributed reward already left the pot.
+        TemplateModule::on_finalize(5);
+        assert_eq!(TemplateModule::owed_rewards(), 1);
+        assert_eq!(Balances::free_balance(&TemplateModule::account_id()), 999);
+
+        assert_noop!(
+            TemplateModule::sweep_pot(Origin::signed(1), 2, 10),
+            DispatchError::BadOrigin
+        );
+        // The pot keeps the existential deposit.
+        assert_noop!(
+            TemplateModule::sweep_pot(Origin::root(), 2, 744),
+            Error::<Test>::SweepExceedsSurplus
+        );
+        assert_ok!(TemplateModule::sweep_pot(Origin::root(), 2, 743));
+        assert_eq!(Balances::free_balance(&2), 1_255);
+        System::assert_last_event(Event::TemplateModule(crate::Event::PotSwept(2, 743)));
+
+        // The owed reward can still be paid.
+        assert_ok!(TemplateModule::claim_rewards(Origin::signed(1)));
+        assert_eq!(StakedBalances::free_balance(&1), 101);
+        assert_eq!(TemplateModule::owed_rewards(), 0);
+        assert_eq!(TemplateModule::total_rewards_paid(), 1);
+    });
+}
+
+#[test]
+fn short_pot_scales_rewards_pro_rata() {
+    new_test_ext().execute_with(|| {
+        for percent in [20, 40] {
+            assert_ok!(TemplateModule::change_percentage(
+                Origin::root(),
+                Perbill::from_percent(percent)
+            ));
+        }
+        for who in 1..=4 {
+            assert_ok!(TemplateModule::stake(Origin::signed(who), 250));
+        }
+        // The pot keeps the existential deposit of 256.
+        let _ = Balances::deposit_creating(&TemplateModule::account_id(), 556);
+
+        // The distribution requires 400, only 300 can be backed.
+        TemplateModule::on_finalize(5);
+        System::assert_last_event(Event::TemplateModule(crate::Event::PotDepleted(300, 400)));
+        assert_eq!(TemplateModule::pending_rewards(&1), 75);
+        assert_eq!(TemplateModule::pending_rewards(&4), 75);
+        assert_eq!(TemplateModule::owed_rewards(), 300);
+
+        // The event is emitted only once while the pot stays depleted.
+        System::reset_events();
+        TemplateModule::on_finalize(10);
+        assert_eq!(System::events().len(), 0);
+        assert_eq!(TemplateModule::pending_rewards(&1), 75);
+
+        for who in 1..=4 {
+            assert_ok!(TemplateModule::claim_rewards(Origin::signed(who)));
+        }
+        assert_eq!(
+            StakedBalances::free_balance(&TemplateModule::account_id()),
+            0
+        );
+        assert_eq!(TemplateModule::total_rewards_paid(), 300);
+    });
+}
+
+#[test]
+fn genesis_config_works() {
+    new_test_ext_with(crate::GenesisConfig {
+        pot: 1_000,
+        percentage: Perbill::from_percent(10),
+        block_to_unlock: 3,
+        reward_period: 7,
+        stakers: vec![(1, 100), (2, 200)],
+    })
+    .execute_with(|| {
+        assert_eq!(TemplateModule::percentage(), Perbill::from_percent(10));
+        assert_eq!(TemplateModule::block_to_unlock(), 3);
+        assert_eq!(TemplateModule::reward_period(), 7);
+        assert_eq!(Balances::free_balance(&TemplateModule::account_id()), 1_000);
+
+        // The initial positions are locked and minted like a `stake`.
+        assert_eq!(TemplateModule::total_pooled(), 300);
+        assert_eq!(TemplateModule::total_shares(), 300);
+        assert_eq!(Balances::free_balance(&1), 412);
+        assert_eq!(Balances::free_balance(&TemplateModule::pool_account()), 556);
+        assert_eq!(StakedBalances::free_balance(&2), 200);
+        let ledger = TemplateModule::ledger(2).unwrap();
+        assert_eq!(ledger.principal, 200);
+        assert_eq!(ledger.minted, 200);
+
+        // Their cooldown started at genesis.
+        System::set_block_number(3);
+        assert_ok!(TemplateModule::unstake(Origin::signed(2), 200));
+    });
+}
+
+#[test]
+#[should_panic(expected = "duplicate staker in `stakers`")]
+fn genesis_rejects_duplicate_stakers() {
+    new_test_ext_with(crate::GenesisConfig {
+        stakers: vec![(1, 100), (1, 100)],
+        ..Default::default()
+    });
+}
+
+#[test]
+#[should_panic(expected = "staker doesn't have enough MainToken")]
+fn genesis_rejects_unfunded_stakers() {
+    new_test_ext_with(crate::GenesisConfig {
+        stakers: vec![(1, 1_000)],
+        ..Default::default()
+    });
+}
+
+#[test]
+#[should_panic(expected = "`reward_period` must be between 1 and `MaxRewardPeriod`")]
+fn genesis_rejects_zero_reward_period() {
+    new_test_ext_with(crate::GenesisConfig {
+        reward_period: 0,
+        ..Default::default()
+    });
+}
+
+#[test]
+fn rewards_use_sub_percent_rates() {
+    new_test_ext().execute_with(|| {
+        let _ = Balances::deposit_creating(&TemplateModule::account_id(), 1_000);
+        // 0.5%, that a whole percent can't express.
+        assert_ok!(TemplateModule::change_percentage(
+            Origin::root(),
+            Perbill::from_parts(5_000_000)
+        ));
+        assert_ok!(TemplateModule::stake(Origin::signed(1), 400));
+        assert_ok!(TemplateModule::stake(Origin::signed(2), 99));
+
+        TemplateModule::on_finalize(5);
+        assert_eq!(TemplateModule::pending_rewards(&1), 2);
+        // 0.495 is rounded down in favour of the pool.
+        assert_eq!(TemplateModule::pending_rewards(&2), 0);
+    });
+}
+
+#[test]
+fn migrate_to_perbill_percentage_works() {
+    new_test_ext().execute_with(|| {
+        let key = crate::Percentage::<Test>::hashed_key();
+        frame_support::storage::unhashed::put_raw(&key, &[7u8]);
+
+        MigrateToPerbillPercentage::<Test>::on_runtime_upgrade();
+        assert_eq!(TemplateModule::percentage(), Perbill::from_percent(7));
+
+        // Running it again doesn't reinterpret the new value.
+        MigrateToPerbillPercentage::<Test>::on_runtime_upgrade();
+        assert_eq!(TemplateModule::percentage(), Perbill::from_percent(7));
+    });
+}
+
+#[test]
+fn parameter_changes_require_the_admin_origin() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(
+            TemplateModule::change_percentage(Origin::signed(1), Perbill::from_percent(5)),
+            DispatchError::BadOrigin
+        );
+        assert_noop!(
+            TemplateModule::change_block_time(Origin::signed(1), 10),
+            DispatchError::BadOrigin
+        );
+        assert_noop!(
+            TemplateModule::sweep_pot(Origin::signed(1), 2, 1),
+            DispatchError::BadOrigin
+        );
+    });
+}
+
+#[test]
+fn parameter_changes_emit_events() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(TemplateModule::change_percentage(
+            Origin::root(),
+            Perbill::from_percent(5)
+        ));
+        System::assert_has_event(Event::TemplateModule(crate::Event::PercentageChanged(
+            Perbill::from_percent(1),
+            Perbill::from_percent(5),
+        )));
+
+        assert_ok!(TemplateModule::change_block_time(Origin::root(), 10));
+        System::assert_has_event(Event::TemplateModule(crate::Event::BlockToUnlockChanged(
+            1, 10,
+        )));
+        assert_eq!(TemplateModule::block_to_unlock(), 10);
+    });
+}
+
+#[test]
+fn parameter_changes_wait_for_the_timelock() {
+    new_test_ext().execute_with(|| {
+        ParameterChangeDelay::set(10);
+        assert_ok!(TemplateModule::change_percentage(
+            Origin::root(),
+            Perbill::from_percent(5)
+        ));
+        System::assert_last_event(Event::TemplateModule(
+            crate::Event::ParameterChangeScheduled(
+                0,
+                ParameterChange::Percentage(Perbill::from_percent(5)),
+                11,
+            ),
+        ));
+        assert_eq!(TemplateModule::pending_changes().len(), 1);
+
+        TemplateModule::on_initialize(10);
+        assert_eq!(TemplateModule::percentage(), Perbill::from_percent(1));
+
+        TemplateModule::on_initialize(11);
+        assert_eq!(TemplateModule::percentage(), Perbill::from_percent(5));
+        assert!(TemplateModule::pending_changes().is_empty());
+        System::assert_last_event(Event::TemplateModule(crate::Event::ParameterChangeEnacted(
+            0,
+            ParameterChange::Percentage(Perbill::from_percent(5)),
+        )));
+    });
+}
+
+#[test]
+fn parameter_changes_can_be_cancelled() {
+    new_test_ext().execute_with(|| {
+        ParameterChangeDelay::set(10);
+        assert_ok!(TemplateModule::change_block_time(Origin::root(), 3));
+        assert_ok!(TemplateModule::change_reward_period(Origin::root(), 7));
+        assert_noop!(
+            TemplateModule::change_block_time(Origin::root(), 4),
+            Error::<Test>::TooManyPendingChanges
+        );
+
+        assert_noop!(
+            TemplateModule::cancel_parameter_change(Origin::signed(1), 0),
+            DispatchError::BadOrigin
+        );
+        assert_noop!(
+            TemplateModule::cancel_parameter_change(Origin::root(), 2),
+            Error::<Test>::UnknownParameterChange
+        );
+        assert_ok!(TemplateModule::cancel_parameter_change(Origin::root(), 0));
+        System::assert_last_event(Event::TemplateModule(
+            crate::Event::ParameterChangeCancelled(0),
+        ));
+
+        TemplateModule::on_initialize(11);
+        assert_eq!(TemplateModule::block_to_unlock(), 1);
+        assert_eq!(TemplateModule::reward_period(), 7);
+    });
+}
+
+#[test]
+fn parameter_changes_respect_the_bounds() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(
+            TemplateModule::change_block_time(Origin::root(), 0),
+            Error::<Test>::BlockToUnlockOutOfBounds
+        );
+        assert_noop!(
+            TemplateModule::change_block_time(Origin::root(), 101),
+            Error::<Test>::BlockToUnlockOutOfBounds
+        );
+        assert_noop!(
+            TemplateModule::change_percentage(Origin::root(), Perbill::from_percent(51)),
+            Error::<Test>::PercentageTooHigh
+        );
+
+        // The percentage moves at most by `MaxStepChange` per update.
+        assert_noop!(
+            TemplateModule::change_percentage(Origin::root(), Perbill::from_percent(22)),
+            Error::<Test>::StepChangeTooLarge
+        );
+        assert_ok!(TemplateModule::change_percentage(
+            Origin::root(),
+            Perbill::from_percent(21)
+        ));
+        assert_ok!(TemplateModule::change_percentage(
+            Origin::root(),
+            Perbill::from_percent(41)
+        ));
+    });
+}
+
+#[test]
+fn steps_are_measured_from_pending_changes() {
+    new_test_ext().execute_with(|| {
+        ParameterChangeDelay::set(10);
+        assert_ok!(TemplateModule::change_percentage(
+            Origin::root(),
+            Perbill::from_percent(21)
+        ));
+        assert_noop!(
+            TemplateModule::change_percentage(Origin::root(), Perbill::from_percent(42)),
+            Error::<Test>::StepChangeTooLarge
+        );
+        assert_ok!(TemplateModule::change_percentage(
+            Origin::root(),
+            Perbill::from_percent(41)
+        ));
+
+        // Both are enacted in order, each within a step of the previous one.
+        TemplateModule::on_initialize(11);
+        assert_eq!(TemplateModule::percentage(), Perbill::from_percent(41));
+    });
+}
+
+#[test]
+fn cancelling_a_step_drops_the_ones_above_it() {
+    new_test_ext().execute_with(|| {
+        ParameterChangeDelay::set(10);
+        assert_ok!(TemplateModule::change_percentage(
+            Origin::root(),
+            Perbill::from_percent(21)
+        ));
+        assert_ok!(TemplateModule::change_percentage(
+            Origin::root(),
+            Perbill::from_percent(41)
+        ));
+        assert_ok!(TemplateModule::cancel_parameter_change(Origin::root(), 0));
+
+        // 41% is now 40% away from the live percentage.
+        TemplateModule::on_initialize(11);
+        assert_eq!(TemplateModule::percentage(), Perbill::from_percent(1));
+        assert!(TemplateModule::pending_changes().is_empty());
+        System::assert_last_event(Event::TemplateModule(crate::Event::ParameterChangeDropped(
+            1,
+            Error::<Test>::StepChangeTooLarge.into(),
+        )));
+    });
+}
+
+#[test]
+fn integrity_test_accepts_the_mock_bounds() {
+    new_test_ext().execute_with(|| TemplateModule::integrity_test());
+}
+
+#[test]
+fn paused_operations_fail() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(TemplateModule::stake(Origin::signed(1), 100));
+        assert_noop!(
+            TemplateModule::pause(Origin::root(), PauseState::ALL),
+            DispatchError::BadOrigin
+        );
+
+        assert_ok!(TemplateModule::pause(Origin::signed(6), PauseState::STAKE));
+        assert_ok!(TemplateModule::pause(
+            Origin::signed(6),
+            PauseState::TRANSFER
+        ));
+        System::assert_last_event(Event::TemplateModule(crate::Event::OperationsPaused(
+            PauseState::TRANSFER,
+            PauseState(0b101),
+        )));
+        assert!(TemplateModule::is_paused(PauseState::STAKE));
+        assert!(!TemplateModule::is_paused(PauseState::UNSTAKE));
+
+        assert_noop!(
+            TemplateModule::stake(Origin::signed(2), 100),
+            Error::<Test>::Paused
+        );
+        assert_noop!(
+            TemplateModule::transfer(Origin::signed(1), 2, 10),
+            Error::<Test>::Paused
+        );
+        System::set_block_number(2);
+        assert_ok!(TemplateModule::unstake(Origin::signed(1), 10));
+
+        assert_ok!(TemplateModule::unpause(
+            Origin::signed(6),
+            PauseState::STAKE
+        ));
+        System::assert_last_event(Event::TemplateModule(crate::Event::OperationsUnpaused(
+            PauseState::STAKE,
+            PauseState::TRANSFER,
+        )));
+        assert_ok!(TemplateModule::stake(Origin::signed(2), 100));
+        assert_eq!(TemplateModule::pause_state(), PauseState::TRANSFER);
+    });
+}
+
+#[test]
+fn pause_rejects_unknown_and_empty_operations() {
+    new_test_ext().execute_with(|| {
+        for ops in [PauseState(0), PauseState(0b1_0000), PauseState(0b1000_0001)] {
+            assert_noop!(
+                TemplateModule::pause(Origin::signed(6), ops),
+                Error::<Test>::InvalidPauseState
+            );
+            assert_noop!(
+                TemplateModule::unpause(Origin::signed(6), ops),
+                Error::<Test>::InvalidPauseState
+            );
+        }
+
+        assert_ok!(TemplateModule::pause(Origin::signed(6), PauseState::ALL));
+        assert_eq!(TemplateModule::pause_state(), PauseState::ALL);
+        assert_ok!(TemplateModule::unpause(Origin::signed(6), PauseState::ALL));
+        assert_eq!(TemplateModule::pause_state(), PauseState::default());
+    });
+}
+
+#[test]
+fn paused_rewards_are_not_distributed() {
+    new_test_ext().execute_with(|| {
+        let _ = Balances::deposit_creating(&TemplateModule::account_id(), 1_000);
+        assert_ok!(TemplateModule::stake(Origin::signed(1), 100));
+        assert_ok!(TemplateModule::pause(
+            Origin::signed(6),
+            PauseState::REWARDS
+        ));
+
+        TemplateModule::on_finalize(5);
+        assert_eq!(TemplateModule::pending_rewards(&1), 0);
+        assert_eq!(TemplateModule::last_reward_block(), 5);
+
+        assert_ok!(TemplateModule::unpause(
+            Origin::signed(6),
+            PauseState::REWARDS
+        ));
+        TemplateModule::on_finalize(10);
+        assert_eq!(TemplateModule::pending_rewards(&1), 1);
+    });
+}
+
+#[test]
+fn paused_rewards_are_not_paid() {
+    new_test_ext().execute_with(|| {
+        let _ = Balances::deposit_creating(&TemplateModule::account_id(), 1_000);
+        assert_ok!(TemplateModule::stake(Origin::signed(1), 100));
+        TemplateModule::on_finalize(5);
+        assert_ok!(TemplateModule::pause(
+            Origin::signed(6),
+            PauseState::REWARDS
+        ));
+
+        assert_noop!(
+            TemplateModule::claim_rewards(Origin::signed(1)),
+            Error::<Test>::Paused
+        );
+        // The other operations settle the rewards without paying them.
+        assert_ok!(TemplateModule::transfer(Origin::signed(1), 2, 10));
+        assert_eq!(StakedBalances::free_balance(&1), 90);
+        assert_eq!(TemplateModule::ledger(1).unwrap().unclaimed, 1);
+        assert_eq!(TemplateModule::pending_rewards(&1), 1);
+
+        assert_ok!(TemplateModule::unpause(
+            Origin::signed(6),
+            PauseState::REWARDS
+        ));
+        assert_ok!(TemplateModule::claim_rewards(Origin::signed(1)));
+        assert_eq!(StakedBalances::free_balance(&1), 91);
+        assert_eq!(TemplateModule::pending_rewards(&1), 0);
+    });
+}
+
+#[test]
+fn stake_limits_are_enforced() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(
+            TemplateModule::change_stake_limits(Origin::root(), 300, 200, 1_000),
+            Error::<Test>::InvalidStakeLimits
+        );
+        assert_ok!(TemplateModule::change_stake_limits(
+            Origin::root(),
+            50,
+            200,
+            300
+        ));
+        System::assert_has_event(Event::TemplateModule(crate::Event::StakeLimitsChanged(
+            50, 200, 300,
+        )));
+
+        assert_noop!(
+            TemplateModule::stake(Origin::signed(1), 49),
+            Error::<Test>::BelowMinimumStake
+        );
+        assert_noop!(
+            TemplateModule::stake(Origin::signed(1), 201),
+            Error::<Test>::AccountCapExceeded
+        );
+        assert_ok!(TemplateModule::stake(Origin::signed(1), 150));
+
+        // The cap applies to the whole position.
+        System::set_block_number(2);
+        assert_noop!(
+            TemplateModule::stake(Origin::signed(1), 51),
+            Error::<Test>::AccountCapExceeded
+        );
+        assert_ok!(TemplateModule::stake(Origin::signed(1), 50));
+
+        assert_ok!(TemplateModule::stake(Origin::signed(2), 100));
+        assert_noop!(
+            TemplateModule::stake(Origin::signed(3), 100),
+            Error::<Test>::PoolCapReached
+        );
+    });
+}
+
+#[test]
+fn unstake_below_minimum_stake_must_exit() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(TemplateModule::change_stake_limits(
+            Origin::root(),
+            50,
+            u64::MAX,
+            u64::MAX
+        ));
+        assert_ok!(TemplateModule::stake(Origin::signed(1), 100));
+        System::set_block_number(2);
+
+        assert_noop!(
+            TemplateModule::unstake(Origin::signed(1), 60),
+            Error::<Test>::BelowMinimumStake
+        );
+        assert_ok!(TemplateModule::unstake(Origin::signed(1), 50));
+        assert_ok!(TemplateModule::unstake(Origin::signed(1), 50));
+        assert_eq!(StakedBalances::free_balance(&1), 0);
+    });
+}
+
+#[test]
+fn previews_match_the_extrinsics() {
+    new_test_ext().execute_with(|| {
+        // An empty pool starts at 1:1.
+        assert_eq!(TemplateModule::preview_stake(100), Some(100));
+        assert_eq!(TemplateModule::preview_unstake(100), Some(100));
+
+        // Unbacked StakedToken moves the exchange rate away from 1:1.
+        assert_ok!(TemplateModule::stake(Origin::signed(1), 100));
+        let _ = StakedBalances::deposit_creating(&3, 1);
+        assert_eq!(
+            TemplateModule::exchange_rate(),
+            FixedU128::saturating_from_rational(100, 101)
+        );
+
+        let preview = TemplateModule::preview_stake(101).unwrap();
+        assert_eq!(preview, 102);
+        assert_ok!(TemplateModule::stake(Origin::signed(2), 101));
+        assert_eq!(StakedBalances::free_balance(&2), preview);
+
+        System::set_block_number(3);
+        let preview = TemplateModule::preview_unstake(50).unwrap();
+        assert_ok!(TemplateModule::unstake(Origin::signed(2), 50));
+        assert_eq!(TemplateModule::unbonding(2)[0].value, preview);
+    });
+}
+
+#[test]
+fn mul_div_works_at_the_limits_of_u64() {
+    assert_eq!(mul_div(u64::MAX, u64::MAX, u64::MAX), Some(u64::MAX));
+    assert_eq!(mul_div(u64::MAX, 2, 1), None);
+    assert_eq!(mul_div(1u64, 1, 0), None);
+
+    assert_eq!(mul_div_up(u64::MAX, 1, 2), Some(u64::MAX / 2 + 1));
+    assert_eq!(
+        mul_div_up(u64::MAX - 1, u64::MAX, u64::MAX),
+        Some(u64::MAX - 1)
+    );
+    assert_eq!(mul_div_up(u64::MAX, u64::MAX, u64::MAX - 1), None);
+}
+
+#[test]
+fn conversions_work_near_u64_max() {
+    new_test_ext().execute_with(|| {
+        let quarter = u64::MAX / 4;
+        Balances::make_free_balance_be(&1, u64::MAX / 2);
+        assert_ok!(TemplateModule::stake(Origin::signed(1), quarter));
+        assert_eq!(StakedBalances::free_balance(&1), quarter);
+
+        // Unbacked StakedToken halves the exchange rate.
+        let _ = StakedBalances::deposit_creating(&3, quarter);
+
+        // `amount * supply` overflows `u64`, the converted amount doesn't.
+        let amount = u64::MAX / 8;
+        assert_eq!(TemplateModule::preview_stake(amount), Some(2 * amount));
+        assert_eq!(TemplateModule::preview_stake(u64::MAX), None);
+
+        Balances::make_free_balance_be(&2, amount + 512);
+        assert_ok!(TemplateModule::stake(Origin::signed(2), amount));
+        assert_eq!(StakedBalances::free_balance(&2), 2 * amount);
+        assert_eq!(TemplateModule::preview_unstake(2 * amount), Some(amount));
+    });
+}
+
+#[test]
+fn next_unlock_block_works() {
+    new_test_ext().execute_with(|| {
+        assert_eq!(TemplateModule::next_unlock_block(&1), None);
+
+        assert_ok!(TemplateModule::stake(Origin::signed(1), 100));
+        assert_eq!(TemplateModule::next_unlock_block(&1), Some(2));
+
+        System::set_block_number(2);
+        assert_eq!(TemplateModule::next_unlock_block(&1), None);
+
+        // The cooldown follows the current `BlockToUnlock`.
+        assert_ok!(TemplateModule::change_block_time(Origin::root(), 10));
+        assert_eq!(TemplateModule::next_unlock_block(&1), Some(11));
+    });
+}
+
+#[test]
+fn pool_totals_works() {
+    new_test_ext().execute_with(|| {
+        let _ = Balances::deposit_creating(&TemplateModule::account_id(), 1_000);
+        assert_ok!(TemplateModule::stake(Origin::signed(1), 100));
+        TemplateModule::on_finalize(5);
+
+        assert_eq!(
+            TemplateModule::pool_totals(),
+            PoolTotals {
+                total_pooled: 101,
+                staked_supply: 101,
+                total_shares: 100,
+                pot: 999,
+                owed_rewards: 1,
+            }
+        );
+    });
+}
+
+#[test]
+fn position_works() {
+    new_test_ext().execute_with(|| {
+        let _ = Balances::deposit_creating(&TemplateModule::account_id(), 1_000);
+        assert_ok!(TemplateModule::stake(Origin::signed(1), 100));
+        TemplateModule::on_finalize(5);
+
+        assert_eq!(
+            TemplateModule::position(&1),
+            Position {
+                staked: 100,
+                value: 100,
+                principal: 100,
+                locked: 100,
+                pending_rewards: 1,
+            }
+        );
+
+        System::set_block_number(2);
+        assert_eq!(TemplateModule::position(&1).locked, 0);
+    });
+}
+
+// Populate the storage of version 0: `staker` staked `reserved` MainToken at block `since`
+// and received `minted` StakedToken.
+fn put_v0_staker(staker: u64, since: u64, reserved: u64, minted: u64) {
+    assert_ok!(Balances::reserve(&staker, reserved));
+    let _ = StakedBalances::deposit_creating(&staker, minted);
+    frame_support::storage::migration::put_storage_value(
+        b"TemplateModule",
+        b"StakedTimes",
+        &Blake2_128Concat::hash(&staker.encode()),
+        since,
+    );
+}
+
+#[test]
+fn migrate_to_v1_works() {
+    new_test_ext().execute_with(|| {
+        StorageVersion::new(0).put::<TemplateModule>();
+        put_v0_staker(1, 3, 100, 102);
+        // Another pallet reserved 50 more on account 2.
+        put_v0_staker(2, 4, 250, 204);
+        // A receiver of a transfer has no `StakedTimes` entry.
+        let _ = StakedBalances::deposit_creating(&3, 50);
+        frame_support::storage::unhashed::put_raw(&crate::Percentage::<Test>::hashed_key(), &[2u8]);
+
+        #[cfg(feature = "try-runtime")]
+        assert_ok!(MigrateToV1::<Test>::pre_upgrade());
+        MigrateToV1::<Test>::on_runtime_upgrade();
+        #[cfg(feature = "try-runtime")]
+        assert_ok!(MigrateToV1::<Test>::post_upgrade());
+
+        assert_eq!(TemplateModule::on_chain_storage_version(), 1);
+        assert_eq!(TemplateModule::percentage(), Perbill::from_percent(2));
+        assert_eq!(
+            TemplateModule::ledger(1).unwrap().chunks.into_inner(),
+            vec![DepositChunk {
+                value: 102,
+                since: 3
+            }]
+        );
+        assert_eq!(TemplateModule::ledger(1).unwrap().principal, 100);
+        assert_eq!(TemplateModule::ledger(2).unwrap().principal, 200);
+        assert_eq!(TemplateModule::ledger(3), None);
+        assert_eq!(TemplateModule::total_shares(), 306);
+        assert_eq!(TotalPooled::<Test>::get(), 300);
+        // The principal moved into the pool account, the other reserve stays.
+        assert_eq!(Balances::free_balance(&TemplateModule::pool_account()), 556);
+        assert_eq!(Balances::reserved_balance(&1), 0);
+        assert_eq!(Balances::reserved_balance(&2), 50);
+        assert!(
+            frame_support::storage::migration::storage_key_iter::<u64, u64, Blake2_128Concat>(
+                b"TemplateModule",
+                b"StakedTimes"
+            )
+            .next()
+            .is_none()
+        );
+
+        // Running it again is a no-op.
+        TotalPooled::<Test>::put(0);
+        MigrateToV1::<Test>::on_runtime_upgrade();
+        assert_eq!(TotalPooled::<Test>::get(), 0);
+        assert_eq!(TemplateModule::percentage(), Perbill::from_percent(2));
+    });
+}
+
+#[test]
+fn migrate_to_v1_discounts_the_rewards_of_version_0() {
+    new_test_ext().execute_with(|| {
+        StorageVersion::new(0).put::<TemplateModule>();
+        frame_support::storage::unhashed::put_raw(&crate::Percentage::<Test>::hashed_key(), &[2u8]);
+        // Account 1 staked 100 at block 3, and the distributions at blocks 5 and 10 raised its
+        // StakedToken from 102 to 106. Another pallet reserved 50 more on it.
+        put_v0_staker(1, 3, 150, 106);
+        System::set_block_number(12);
+
+        #[cfg(feature = "try-runtime")]
+        assert_ok!(MigrateToV1::<Test>::pre_upgrade());
+        MigrateToV1::<Test>::on_runtime_upgrade();
+        #[cfg(feature = "try-runtime")]
+        assert_ok!(MigrateToV1::<Test>::post_upgrade());
+
+        // `106 / 1.02^3` rounded down: the rewards are not priced as principal.
+        assert_eq!(TemplateModule::ledger(1).unwrap().principal, 99);
+        assert_eq!(TotalPooled::<Test>::get(), 99);
+        // The other reserve stays, with the rounding of the principal.
+        assert_eq!(Balances::reserved_balance(&1), 51);
+    });
+}
+
+#[test]
+fn migrate_to_v1_keeps_the_stakers_usable() {
+    new_test_ext().execute_with(|| {
+        StorageVersion::new(0).put::<TemplateModule>();
+        PoolCustody::set(CustodyMode::Reserve);
+        frame_support::storage::unhashed::put_raw(&crate::Percentage::<Test>::hashed_key(), &[1u8]);
+        // Another pallet reserved 50 more on account 1.
+        put_v0_staker(1, 1, 150, 101);
+
+        MigrateToV1::<Test>::on_runtime_upgrade();
+        assert_eq!(
+            Balances::reserved_balance_named(&TemplateModule::reserve_id(), &1),
+            100
+        );
+        assert_eq!(Balances::reserved_balance(&1), 150);
+
+        System::set_block_number(2);
+        assert_ok!(TemplateModule::unstake(Origin::signed(1), 101));
+        System::set_block_number(3);
+        assert_ok!(TemplateModule::withdraw_unbonded(Origin::signed(1)));
+        assert_eq!(Balances::free_balance(&1), 462);
+        assert_eq!(Balances::reserved_balance(&1), 50);
+    });
+}
+
+#[test]
+fn migrate_to_v1_leaves_a_reserve_it_cant_name() {
+    new_test_ext().execute_with(|| {
+        StorageVersion::new(0).put::<TemplateModule>();
+        PoolCustody::set(CustodyMode::Reserve);
+        frame_support::storage::unhashed::put_raw(&crate::Percentage::<Test>::hashed_key(), &[1u8]);
+        put_v0_staker(1, 1, 100, 101);
+        // A vesting-like lock forbids reserving the funds again once unreserved.
+        Balances::set_lock(*b"vesting ", &1, 1_000, WithdrawReasons::RESERVE);
+
+        MigrateToV1::<Test>::on_runtime_upgrade();
+        System::assert_has_event(Event::TemplateModule(crate::Event::ReserveNotConverted(
+            1,
+            100,
+            pallet_balances::Error::<Test, pallet_balances::Instance1>::LiquidityRestrictions
+                .into(),
+        )));
+
+        // The reserve stays anonymous and the pool doesn't count it.
+        assert_eq!(Balances::reserved_balance(&1), 100);
+        assert_eq!(
+            Balances::reserved_balance_named(&TemplateModule::reserve_id(), &1),
+            0
+        );
+        assert_eq!(TemplateModule::ledger(1).unwrap().principal, 0);
+        assert_eq!(TotalPooled::<Test>::get(), 0);
+    });
+}
+
+#[test]
+fn storage_version_is_set_at_genesis() {
+    new_test_ext().execute_with(|| {
+        assert_eq!(TemplateModule::on_chain_storage_version(), 1);
+        assert_eq!(
+            TemplateModule::on_chain_storage_version(),
+            TemplateModule::current_storage_version()
+        );
+    });
+}
+
+#[test]
+fn try_state_holds_after_operations() {
+    for custody in [CustodyMode::PoolAccount, CustodyMode::Reserve] {
+        new_test_ext().execute_with(|| {
+            PoolCustody::set(custody);
+            let _ = Balances::deposit_creating(&TemplateModule::account_id(), 1_000);
+            assert_ok!(TemplateModule::do_try_state());
+
+            assert_ok!(TemplateModule::stake(Origin::signed(1), 100));
+            assert_ok!(TemplateModule::stake(Origin::signed(2), 200));
+            TemplateModule::on_finalize(5);
+            assert_ok!(TemplateModule::do_try_state());
+
+            assert_ok!(TemplateModule::transfer(Origin::signed(1), 3, 50));
+            assert_ok!(TemplateModule::do_try_state());
+
+            System::set_block_number(6);
+            assert_ok!(TemplateModule::unstake(Origin::signed(2), 100));
+            assert_ok!(TemplateModule::do_try_state());
+
+            System::set_block_number(8);
+            assert_ok!(TemplateModule::withdraw_unbonded(Origin::signed(2)));
+            TemplateModule::on_finalize(10);
+            assert_ok!(TemplateModule::claim_rewards(Origin::signed(2)));
+            assert_ok!(TemplateModule::do_try_state());
+        });
+    }
+}
+
+#[test]
+fn try_state_detects_broken_invariants() {
+    new_test_ext().execute_with(|| {
+        PoolCustody::set(CustodyMode::Reserve);
+        assert_ok!(TemplateModule::stake(Origin::signed(1), 100));
+        TotalPooled::<Test>::put(50);
+        assert_eq!(
+            TemplateModule::do_try_state(),
+            Err("the ledgers and the pot don't add up to `TotalPooled`")
+        );
+    });
+
+    new_test_ext().execute_with(|| {
+        assert_ok!(TemplateModule::stake(Origin::signed(1), 100));
+        TotalShares::<Test>::put(90);
+        assert_eq!(
+            TemplateModule::do_try_state(),
+            Err("the ledgers don't add up to `TotalShares`")
+        );
+    });
+
+    new_test_ext().execute_with(|| {
+        assert_ok!(TemplateModule::stake(Origin::signed(1), 100));
+        let _ = StakedBalances::deposit_creating(&2, 10);
+        assert_eq!(
+            TemplateModule::check_invariants(),
+            Some(Invariant::IssuanceAboveMinted)
+        );
+        assert_eq!(
+            TemplateModule::do_try_state(),
+            Err("the StakedToken issuance is above the minted and not burned StakedToken")
+        );
+    });
+
+    new_test_ext().execute_with(|| {
+        let _ = Balances::deposit_creating(&TemplateModule::account_id(), 1_000);
+        assert_ok!(TemplateModule::stake(Origin::signed(1), 100));
+        assert_ok!(TemplateModule::set_reward_destination(
+            Origin::signed(1),
+            RewardDestination::Free
+        ));
+        TemplateModule::on_finalize(5);
+        assert_ok!(TemplateModule::claim_rewards(Origin::signed(1)));
+        // The reward is not a share, so burning it outside of the pool keeps the issuance
+        // above the shares.
+        let _ = StakedBalances::slash(&1, 1);
+        assert_eq!(
+            TemplateModule::do_try_state(),
+            Err("the StakedToken issuance doesn't match the minted and not burned StakedToken")
+        );
+    });
+
+    new_test_ext().execute_with(|| {
+        assert_ok!(TemplateModule::stake(Origin::signed(1), 100));
+        crate::Ledger::<Test>::mutate(1, |ledger| {
+            let ledger = ledger.as_mut().unwrap();
+            ledger.chunks.get_mut(0).unwrap().value += 1_000;
+            ledger.minted += 1_000;
+        });
+        assert_eq!(
+            TemplateModule::do_try_state(),
+            Err("the ledgers track more StakedToken than the staked supply")
+        );
+    });
+}
+
+#[test]
+fn violated_invariants_pause_staking() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(TemplateModule::stake(Origin::signed(1), 100));
+
+        // Without the check nothing happens.
+        OwedRewards::<Test>::put(10);
+        TemplateModule::on_finalize(2);
+        assert!(!TemplateModule::is_paused(PauseState::STAKE));
+
+        CheckInvariants::set(true);
+        assert_eq!(
+            TemplateModule::check_invariants(),
+            Some(Invariant::PotBelowOwedRewards)
+        );
+        TemplateModule::on_finalize(3);
+        assert_eq!(TemplateModule::pause_state(), PauseState::STAKE);
+        System::assert_has_event(Event::TemplateModule(crate::Event::OperationsPaused(
+            PauseState::STAKE,
+            PauseState::STAKE,
+        )));
+        System::assert_last_event(Event::TemplateModule(crate::Event::InvariantViolated(
+            Invariant::PotBelowOwedRewards,
+        )));
+        assert_noop!(
+            TemplateModule::stake(Origin::signed(2), 100),
+            Error::<Test>::Paused
+        );
+
+        // The violation is reported once, and the other operations keep working.
+        System::reset_events();
+        TemplateModule::on_finalize(4);
+        assert!(System::events().is_empty());
+        System::set_block_number(2);
+        assert_ok!(TemplateModule::unstake(Origin::signed(1), 100));
+
+        // Once fixed, the governance can resume staking.
+        OwedRewards::<Test>::kill();
+        assert_eq!(TemplateModule::check_invariants(), None);
+        assert_ok!(TemplateModule::unpause(
+            Origin::signed(6),
+            PauseState::STAKE
+        ));
+        assert_ok!(TemplateModule::stake(Origin::signed(2), 100));
+    });
+}
+
+#[test]
+fn rewards_compound_by_default() {
+    new_test_ext().execute_with(|| {
+        let _ = Balances::deposit_creating(&TemplateModule::account_id(), 1_000);
+        assert_ok!(TemplateModule::stake(Origin::signed(1), 100));
+        TemplateModule::on_finalize(5);
+        assert_eq!(TemplateModule::payee(1), RewardDestination::Compound);
+
+        assert_ok!(TemplateModule::claim_rewards(Origin::signed(1)));
+        assert_eq!(StakedBalances::free_balance(&1), 101);
+        assert_eq!(TemplateModule::ledger(1).unwrap().minted, 101);
+        assert_eq!(TemplateModule::total_shares(), 101);
+        System::assert_last_event(Event::TemplateModule(crate::Event::RewardPaid(
+            1,
+            RewardDestination::Compound,
+            1,
+        )));
+    });
+}
+
+#[test]
+fn rewards_can_be_paid_to_the_free_balance() {
+    new_test_ext().execute_with(|| {
+        let _ = Balances::deposit_creating(&TemplateModule::account_id(), 1_000);
+        assert_ok!(TemplateModule::stake(Origin::signed(1), 100));
+        assert_ok!(TemplateModule::set_reward_destination(
+            Origin::signed(1),
+            RewardDestination::Free
+        ));
+        System::assert_last_event(Event::TemplateModule(crate::Event::RewardDestinationSet(
+            1,
+            RewardDestination::Free,
+        )));
+        TemplateModule::on_finalize(5);
+
+        assert_ok!(TemplateModule::claim_rewards(Origin::signed(1)));
+        assert_eq!(StakedBalances::free_balance(&1), 101);
+        // The reward is not tracked by the ledger: no cooldown and no rewards.
+        assert_eq!(TemplateModule::ledger(1).unwrap().minted, 100);
+        assert_eq!(TemplateModule::total_shares(), 100);
+        System::assert_last_event(Event::TemplateModule(crate::Event::RewardPaid(
+            1,
+            RewardDestination::Free,
+            1,
+        )));
+        assert_ok!(TemplateModule::do_try_state());
+    });
+}
+
+#[test]
+fn rewards_can_be_paid_to_another_account() {
+    new_test_ext().execute_with(|| {
+        let _ = Balances::deposit_creating(&TemplateModule::account_id(), 1_000);
+        assert_ok!(TemplateModule::stake(Origin::signed(1), 100));
+        assert_noop!(
+            TemplateModule::set_reward_destination(
+                Origin::signed(1),
+                RewardDestination::Account(TemplateModule::account_id())
+            ),
+            Error::<Test>::InvalidRewardDestination
+        );
+        assert_ok!(TemplateModule::set_reward_destination(
+            Origin::signed(1),
+            RewardDestination::Account(3)
+        ));
+        TemplateModule::on_finalize(5);
+
+        assert_ok!(TemplateModule::claim_rewards(Origin::signed(1)));
+        assert_eq!(StakedBalances::free_balance(&1), 100);
+        assert_eq!(StakedBalances::free_balance(&3), 1);
+        assert_eq!(TemplateModule::ledger(3), None);
+        System::assert_last_event(Event::TemplateModule(crate::Event::RewardPaid(
+            1,
+            RewardDestination::Account(3),
+            1,
+        )));
+    });
+}
+
+#[test]
+fn changing_the_reward_destination_pays_the_old_one() {
+    new_test_ext().execute_with(|| {
+        let _ = Balances::deposit_creating(&TemplateModule::account_id(), 1_000);
+        assert_ok!(TemplateModule::stake(Origin::signed(1), 100));
+        TemplateModule::on_finalize(5);
+
+        // The reward accrued while compounding is compounded.
+        assert_ok!(TemplateModule::set_reward_destination(
+            Origin::signed(1),
+            RewardDestination::Account(3)
+        ));
+        assert_eq!(TemplateModule::ledger(1).unwrap().minted, 101);
+        assert_eq!(StakedBalances::free_balance(&3), 0);
+        assert_eq!(TemplateModule::payee(1), RewardDestination::Account(3));
+
+        // Setting it back to `Compound` clears the storage.
+        assert_ok!(TemplateModule::set_reward_destination(
+            Origin::signed(1),
+            RewardDestination::Compound
+        ));
+        assert!(!crate::Payee::<Test>::contains_key(1));
     });
 }
@@ -0,0 +1,363 @@
+//! Weights for the simple pool pallet.
+//!
+//! The values below are placeholders, not benchmark results: this repository has no node to run
+//! the benchmarks in `benchmarking.rs`, so the storage accesses are counted by hand from the worst
+//! path of each call, listing the storage of both custody modes, and the execution times are rough
+//! estimates. The benchmarks set up that worst path, with a full `MaxDepositChunks` ledger and a
+//! full `MaxUnbondingChunks` queue. Regenerate the weights for your runtime before using them in
+//! production, with the template of Substrate's `.maintain/frame-weight-template.hbs`:
+//!
+//! ```text
+//! ./target/release/node-template benchmark pallet \
+//!     --chain=dev --steps=50 --repeat=20 \
+//!     --pallet=simple_pool --extrinsic='*' \
+//!     --execution=wasm --wasm-execution=compiled \
+//!     --output=./src/weights.rs --template=./.maintain/frame-weight-template.hbs
+//! ```
+
+#![cfg_attr(rustfmt, rustfmt_skip)]
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use core::marker::PhantomData;
+use frame_support::{traits::Get, weights::{Weight, constants::RocksDbWeight}};
+
+/// Weight functions needed for the simple pool pallet.
+pub trait WeightInfo {
+	fn stake() -> Weight;
+	fn unstake() -> Weight;
+	fn withdraw_unbonded() -> Weight;
+	fn transfer() -> Weight;
+	fn claim_rewards() -> Weight;
+	fn set_reward_destination() -> Weight;
+	fn fund_pot() -> Weight;
+	fn sweep_pot() -> Weight;
+	fn change_percentage() -> Weight;
+	fn change_block_time() -> Weight;
+	fn change_reward_period() -> Weight;
+	fn change_stake_limits() -> Weight;
+	fn cancel_parameter_change() -> Weight;
+	fn pause() -> Weight;
+	fn unpause() -> Weight;
+	fn on_finalize_rewards(s: u32, ) -> Weight;
+	fn on_initialize_enact(c: u32, ) -> Weight;
+	fn check_invariants() -> Weight;
+}
+
+/// Weights for the simple pool pallet using the Substrate node and recommended hardware.
+pub struct SubstrateWeight<T>(PhantomData<T>);
+impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
+	// Storage: SimplePool Paused (r:1 w:0)
+	// Storage: MainToken Account (r:2 w:2)
+	// Storage: MainToken Reserves (r:2 w:2)
+	// Storage: StakedToken Account (r:3 w:3)
+	// Storage: StakedToken TotalIssuance (r:1 w:1)
+	// Storage: SimplePool Ledger (r:1 w:1)
+	// Storage: SimplePool BlockToUnlock (r:1 w:0)
+	// Storage: SimplePool TotalPooled (r:1 w:1)
+	// Storage: SimplePool MaxTotalStaked (r:1 w:0)
+	// Storage: SimplePool MinStake (r:1 w:0)
+	// Storage: SimplePool MaxStakePerAccount (r:1 w:0)
+	// Storage: SimplePool Payee (r:1 w:0)
+	// Storage: SimplePool RewardPerShare (r:1 w:0)
+	// Storage: SimplePool RewardDebt (r:1 w:1)
+	// Storage: SimplePool TotalShares (r:1 w:1)
+	// Storage: SimplePool OwedRewards (r:1 w:1)
+	// Storage: SimplePool TotalRewardsPaid (r:1 w:1)
+	// Storage: SimplePool TotalMinted (r:1 w:1)
+	fn stake() -> Weight {
+		(95_000_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(22 as Weight))
+			.saturating_add(T::DbWeight::get().writes(15 as Weight))
+	}
+	// Storage: SimplePool Paused (r:1 w:0)
+	// Storage: MainToken Account (r:2 w:2)
+	// Storage: MainToken Reserves (r:2 w:2)
+	// Storage: StakedToken Account (r:3 w:3)
+	// Storage: StakedToken TotalIssuance (r:1 w:1)
+	// Storage: SimplePool MinStake (r:1 w:0)
+	// Storage: SimplePool TotalPooled (r:1 w:1)
+	// Storage: SimplePool Ledger (r:1 w:1)
+	// Storage: SimplePool BlockToUnlock (r:1 w:0)
+	// Storage: SimplePool Unbonding (r:1 w:1)
+	// Storage: SimplePool Payee (r:1 w:0)
+	// Storage: SimplePool RewardPerShare (r:1 w:0)
+	// Storage: SimplePool RewardDebt (r:1 w:1)
+	// Storage: SimplePool TotalShares (r:1 w:1)
+	// Storage: SimplePool OwedRewards (r:1 w:1)
+	// Storage: SimplePool TotalRewardsPaid (r:1 w:1)
+	// Storage: SimplePool TotalBurned (r:1 w:1)
+	fn unstake() -> Weight {
+		(90_000_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(21 as Weight))
+			.saturating_add(T::DbWeight::get().writes(16 as Weight))
+	}
+	// Storage: SimplePool Unbonding (r:1 w:1)
+	// Storage: MainToken Account (r:2 w:2)
+	// Storage: MainToken Reserves (r:1 w:1)
+	fn withdraw_unbonded() -> Weight {
+		(45_000_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(4 as Weight))
+			.saturating_add(T::DbWeight::get().writes(4 as Weight))
+	}
+	// Storage: SimplePool Paused (r:1 w:0)
+	// Storage: StakedToken Account (r:5 w:5)
+	// Storage: SimplePool Ledger (r:2 w:2)
+	// Storage: SimplePool BlockToUnlock (r:1 w:0)
+	// Storage: SimplePool Payee (r:2 w:0)
+	// Storage: SimplePool RewardPerShare (r:1 w:0)
+	// Storage: SimplePool RewardDebt (r:2 w:2)
+	// Storage: SimplePool TotalShares (r:1 w:1)
+	// Storage: SimplePool OwedRewards (r:1 w:1)
+	// Storage: SimplePool TotalRewardsPaid (r:1 w:1)
+	// Storage: MainToken Account (r:3 w:3)
+	// Storage: MainToken Reserves (r:3 w:3)
+	fn transfer() -> Weight {
+		(110_000_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(23 as Weight))
+			.saturating_add(T::DbWeight::get().writes(18 as Weight))
+	}
+	// Storage: SimplePool Paused (r:1 w:0)
+	// Storage: SimplePool Ledger (r:1 w:1)
+	// Storage: SimplePool Payee (r:1 w:0)
+	// Storage: SimplePool RewardPerShare (r:1 w:0)
+	// Storage: SimplePool RewardDebt (r:1 w:1)
+	// Storage: StakedToken Account (r:3 w:2)
+	// Storage: SimplePool TotalShares (r:1 w:1)
+	// Storage: SimplePool OwedRewards (r:1 w:1)
+	// Storage: SimplePool TotalRewardsPaid (r:1 w:1)
+	// Storage: MainToken Account (r:2 w:2)
+	// Storage: MainToken Reserves (r:2 w:2)
+	fn claim_rewards() -> Weight {
+		(60_000_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(15 as Weight))
+			.saturating_add(T::DbWeight::get().writes(11 as Weight))
+	}
+	// Storage: SimplePool Paused (r:1 w:0)
+	// Storage: SimplePool Ledger (r:1 w:1)
+	// Storage: SimplePool Payee (r:1 w:1)
+	// Storage: SimplePool RewardPerShare (r:1 w:0)
+	// Storage: SimplePool RewardDebt (r:1 w:1)
+	// Storage: StakedToken Account (r:3 w:2)
+	// Storage: SimplePool TotalShares (r:1 w:1)
+	// Storage: SimplePool OwedRewards (r:1 w:1)
+	// Storage: SimplePool TotalRewardsPaid (r:1 w:1)
+	// Storage: MainToken Account (r:2 w:2)
+	// Storage: MainToken Reserves (r:2 w:2)
+	fn set_reward_destination() -> Weight {
+		(62_000_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(15 as Weight))
+			.saturating_add(T::DbWeight::get().writes(12 as Weight))
+	}
+	// Storage: MainToken Account (r:2 w:2)
+	fn fund_pot() -> Weight {
+		(35_000_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(2 as Weight))
+			.saturating_add(T::DbWeight::get().writes(2 as Weight))
+	}
+	// Storage: MainToken Account (r:2 w:2)
+	fn sweep_pot() -> Weight {
+		(38_000_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(2 as Weight))
+			.saturating_add(T::DbWeight::get().writes(2 as Weight))
+	}
+	// Storage: SimplePool PendingChanges (r:1 w:1)
+	// Storage: SimplePool Percentage (r:1 w:1)
+	// Storage: SimplePool NextChangeId (r:1 w:1)
+	fn change_percentage() -> Weight {
+		(22_000_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(3 as Weight))
+			.saturating_add(T::DbWeight::get().writes(3 as Weight))
+	}
+	// Storage: SimplePool PendingChanges (r:1 w:1)
+	// Storage: SimplePool Percentage (r:1 w:0)
+	// Storage: SimplePool BlockToUnlock (r:1 w:1)
+	// Storage: SimplePool NextChangeId (r:1 w:1)
+	fn change_block_time() -> Weight {
+		(20_000_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(4 as Weight))
+			.saturating_add(T::DbWeight::get().writes(3 as Weight))
+	}
+	// Storage: SimplePool PendingChanges (r:1 w:1)
+	// Storage: SimplePool Percentage (r:1 w:0)
+	// Storage: SimplePool RewardPeriod (r:1 w:1)
+	// Storage: SimplePool NextChangeId (r:1 w:1)
+	fn change_reward_period() -> Weight {
+		(20_000_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(4 as Weight))
+			.saturating_add(T::DbWeight::get().writes(3 as Weight))
+	}
+	// Storage: SimplePool PendingChanges (r:1 w:1)
+	// Storage: SimplePool Percentage (r:1 w:0)
+	// Storage: SimplePool NextChangeId (r:1 w:1)
+	// Storage: SimplePool MinStake (r:0 w:1)
+	// Storage: SimplePool MaxStakePerAccount (r:0 w:1)
+	// Storage: SimplePool MaxTotalStaked (r:0 w:1)
+	fn change_stake_limits() -> Weight {
+		(21_000_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(3 as Weight))
+			.saturating_add(T::DbWeight::get().writes(5 as Weight))
+	}
+	// Storage: SimplePool PendingChanges (r:1 w:1)
+	fn cancel_parameter_change() -> Weight {
+		(18_000_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(1 as Weight))
+			.saturating_add(T::DbWeight::get().writes(1 as Weight))
+	}
+	// Storage: SimplePool Paused (r:1 w:1)
+	fn pause() -> Weight {
+		(15_000_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(1 as Weight))
+			.saturating_add(T::DbWeight::get().writes(1 as Weight))
+	}
+	// Storage: SimplePool Paused (r:1 w:1)
+	fn unpause() -> Weight {
+		(15_000_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(1 as Weight))
+			.saturating_add(T::DbWeight::get().writes(1 as Weight))
+	}
+	// Storage: SimplePool LastRewardBlock (r:1 w:1)
+	// Storage: SimplePool RewardPeriod (r:1 w:0)
+	// Storage: SimplePool Paused (r:1 w:0)
+	// Storage: SimplePool TotalShares (r:1 w:0)
+	// Storage: SimplePool Percentage (r:1 w:0)
+	// Storage: StakedToken Account (r:1 w:1)
+	// Storage: StakedToken TotalIssuance (r:1 w:1)
+	// Storage: MainToken Account (r:2 w:2)
+	// Storage: MainToken Reserves (r:1 w:1)
+	// Storage: SimplePool TotalPooled (r:1 w:1)
+	// Storage: SimplePool OwedRewards (r:1 w:1)
+	// Storage: SimplePool PotDepletionReported (r:1 w:1)
+	// Storage: SimplePool RewardPerShare (r:1 w:1)
+	/// The range of component `s` is `[1, 100]`.
+	fn on_finalize_rewards(_s: u32, ) -> Weight {
+		(25_000_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(14 as Weight))
+			.saturating_add(T::DbWeight::get().writes(10 as Weight))
+	}
+	// Storage: SimplePool PendingChanges (r:1 w:1)
+	// Storage: SimplePool Percentage (r:1 w:1)
+	// Storage: SimplePool MinStake (r:0 w:1)
+	// Storage: SimplePool MaxStakePerAccount (r:0 w:1)
+	// Storage: SimplePool MaxTotalStaked (r:0 w:1)
+	// Every enacted change reads `Percentage` and writes at most the three stake limits.
+	/// The range of component `c` is `[0, 16]`.
+	fn on_initialize_enact(c: u32, ) -> Weight {
+		(4_000_000 as Weight)
+			// Standard Error: 2_000
+			.saturating_add((6_000_000 as Weight).saturating_mul(c as Weight))
+			.saturating_add(T::DbWeight::get().reads(1 as Weight))
+			.saturating_add(T::DbWeight::get().reads((1 as Weight).saturating_mul(c as Weight)))
+			.saturating_add(T::DbWeight::get().writes(1 as Weight))
+			.saturating_add(T::DbWeight::get().writes((3 as Weight).saturating_mul(c as Weight)))
+	}
+	// Storage: SimplePool Paused (r:1 w:1)
+	// Storage: StakedToken Account (r:1 w:0)
+	// Storage: SimplePool OwedRewards (r:1 w:0)
+	// Storage: MainToken Account (r:1 w:0)
+	// Storage: SimplePool TotalPooled (r:1 w:0)
+	// Storage: StakedToken TotalIssuance (r:1 w:0)
+	// Storage: SimplePool TotalShares (r:1 w:0)
+	fn check_invariants() -> Weight {
+		(12_000_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(7 as Weight))
+			.saturating_add(T::DbWeight::get().writes(1 as Weight))
+	}
+}
+
+// For backwards compatibility and tests
+impl WeightInfo for () {
+	fn stake() -> Weight {
+		(95_000_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(22 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(15 as Weight))
+	}
+	fn unstake() -> Weight {
+		(90_000_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(21 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(16 as Weight))
+	}
+	fn withdraw_unbonded() -> Weight {
+		(45_000_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(4 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(4 as Weight))
+	}
+	fn transfer() -> Weight {
+		(110_000_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(23 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(18 as Weight))
+	}
+	fn claim_rewards() -> Weight {
+		(60_000_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(15 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(11 as Weight))
+	}
+	fn set_reward_destination() -> Weight {
+		(62_000_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(15 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(12 as Weight))
+	}
+	fn fund_pot() -> Weight {
+		(35_000_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(2 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(2 as Weight))
+	}
+	fn sweep_pot() -> Weight {
+		(38_000_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(2 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(2 as Weight))
+	}
+	fn change_percentage() -> Weight {
+		(22_000_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(3 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(3 as Weight))
+	}
+	fn change_block_time() -> Weight {
+		(20_000_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(4 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(3 as Weight))
+	}
+	fn change_reward_period() -> Weight {
+		(20_000_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(4 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(3 as Weight))
+	}
+	fn change_stake_limits() -> Weight {
+		(21_000_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(3 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(5 as Weight))
+	}
+	fn cancel_parameter_change() -> Weight {
+		(18_000_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(1 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(1 as Weight))
+	}
+	fn pause() -> Weight {
+		(15_000_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(1 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(1 as Weight))
+	}
+	fn unpause() -> Weight {
+		(15_000_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(1 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(1 as Weight))
+	}
+	fn on_finalize_rewards(_s: u32, ) -> Weight {
+		(25_000_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(14 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(10 as Weight))
+	}
+	fn on_initialize_enact(c: u32, ) -> Weight {
+		(4_000_000 as Weight)
+			.saturating_add((6_000_000 as Weight).saturating_mul(c as Weight))
+			.saturating_add(RocksDbWeight::get().reads(1 as Weight))
+			.saturating_add(RocksDbWeight::get().reads((1 as Weight).saturating_mul(c as Weight)))
+			.saturating_add(RocksDbWeight::get().writes(1 as Weight))
+			.saturating_add(RocksDbWeight::get().writes((3 as Weight).saturating_mul(c as Weight)))
+	}
+	fn check_invariants() -> Weight {
+		(12_000_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(7 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(1 as Weight))
+	}
+}